@@ -1,32 +1,67 @@
 use std::fmt;
+use std::mem;
 use parse::Executable;
 use instruction;
 use instruction::{Instruction, Condition, Operand};
 use port::{CpuWritePorts, ReadPort};
 
-#[derive(Clone, Copy, Debug, PartialEq)]
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
 pub enum ExecState {
+    #[default]
     EXEC,
     READ(instruction::Port),
     WRITE(instruction::Port),
 }
 
-impl Default for ExecState {
-    fn default() -> Self {
-        ExecState::EXEC
-    }
-}
-
 impl fmt::Display for ExecState {
     fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
         f.write_str(match *self {
             ExecState::EXEC     => "EXEC",
             ExecState::READ(_)  => "READ",
-            ExecState::WRITE(_) => "EXEC",
+            ExecState::WRITE(_) => "WRTE",
         })
     }
 }
 
+/// Range of values a node can store
+///
+/// Results of arithmetic and moves saturate at the bounds. The fields are
+/// private so every range goes through new(), which checks min <= max.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct WordRange {
+    min: i32,
+    max: i32,
+}
+
+impl Default for WordRange {
+    /// The range of the game's T21 node
+    fn default() -> Self {
+        WordRange {
+            min: instruction::LIT_MIN,
+            max: instruction::LIT_MAX,
+        }
+    }
+}
+
+impl WordRange {
+    pub fn new(min: i32, max: i32) -> Self {
+        assert!(min <= max);
+        WordRange { min, max }
+    }
+
+    pub fn min(&self) -> i32 {
+        self.min
+    }
+
+    pub fn max(&self) -> i32 {
+        self.max
+    }
+
+    pub fn clamp(&self, val: i32) -> i32 {
+        val.clamp(self.min, self.max)
+    }
+}
+
 #[derive(Default)]
 pub struct CpuState {
     pub acc:        i32,
@@ -37,20 +72,30 @@ pub struct CpuState {
 }
 
 pub struct CpuReadPorts<'a> {
-    up:     &'a ReadPort,
-    down:   &'a ReadPort,
-    left:   &'a ReadPort,
-    right:  &'a ReadPort,
+    up:     Box<dyn ReadPort + 'a>,
+    down:   Box<dyn ReadPort + 'a>,
+    left:   Box<dyn ReadPort + 'a>,
+    right:  Box<dyn ReadPort + 'a>,
 }
 
 impl<'a> CpuReadPorts<'a> {
+    pub fn new<U, D, L, R>(up: U, down: D, left: L, right: R) -> Self
+        where U: ReadPort + 'a, D: ReadPort + 'a, L: ReadPort + 'a, R: ReadPort + 'a {
+        CpuReadPorts {
+            up:     Box::new(up),
+            down:   Box::new(down),
+            left:   Box::new(left),
+            right:  Box::new(right),
+        }
+    }
+
     /// Index ports structure by instruction port enum
-    fn get_port(&self, p: instruction::Port) -> &ReadPort {
+    pub fn get_port(&self, p: instruction::Port) -> &dyn ReadPort {
         match p {
-            instruction::Port::Up =>    self.up,
-            instruction::Port::Down =>  self.down,
-            instruction::Port::Left =>  self.left,
-            instruction::Port::Right => self.right,
+            instruction::Port::Up =>    &*self.up,
+            instruction::Port::Down =>  &*self.down,
+            instruction::Port::Left =>  &*self.left,
+            instruction::Port::Right => &*self.right,
             _ => panic!("Invalid port")
         }
     }
@@ -64,7 +109,7 @@ impl<'a> CpuReadPorts<'a> {
                              instruction::Port::Left,
                              instruction::Port::Right].iter() {
                     ret = self.get_port(*port).read();
-                    if let Some(_) = ret {
+                    if ret.is_some() {
                         *last = *port;
                         break;
                     }
@@ -82,17 +127,24 @@ impl<'a> CpuReadPorts<'a> {
 struct CpuPorts<'a> {
     outports:   &'a CpuWritePorts,
     inports:    CpuReadPorts<'a>,
-    last:       instruction::Port,
+    /// Port that LAST refers to, or None until ANY has picked one. LAST
+    /// behaves as UP until then.
+    last:       Option<instruction::Port>,
 }
 
 impl<'a> CpuPorts<'a> {
     fn read_port(&mut self, port: instruction::Port) -> Option<i32> {
-        self.inports.read_port(port, &mut self.last)
+        let mut last = self.last.unwrap_or(instruction::Port::Up);
+        let val = self.inports.read_port(port, &mut last);
+        if val.is_some() && port == instruction::Port::Any {
+            self.last = Some(last);
+        }
+        val
     }
 
     fn write_port(&mut self, port: instruction::Port, val: i32) {
         self.outports.write_port(match port {
-            instruction::Port::Last => self.last,
+            instruction::Port::Last => self.last.unwrap_or(instruction::Port::Up),
             _ => port,
         }, val)
     }
@@ -100,7 +152,7 @@ impl<'a> CpuPorts<'a> {
     fn write_finished(&mut self, port: instruction::Port) -> bool {
         let finished = self.outports.write_finished();
         if finished && port == instruction::Port::Any {
-            self.last = self.outports.get_last();
+            self.last = Some(self.outports.get_last());
         }
         finished
     }
@@ -110,15 +162,17 @@ pub struct Cpu<'a> {
     state:      CpuState,
     ports:      CpuPorts<'a>,
     executable: Executable,
+    range:      WordRange,
 }
 
 fn get_operand(state: &mut CpuState, ports: &mut CpuPorts, op: &Operand) -> Option<i32> {
-    match op {
-        &Operand::Lit(i) => Some(i),
-        &Operand::ACC => Some(state.acc),
-        &Operand::Port(p) => {
+    match *op {
+        Operand::Lit(i) => Some(i),
+        Operand::ACC => Some(state.acc),
+        Operand::NIL => Some(0),
+        Operand::Port(p) => {
             let val = ports.read_port(p);
-            if val == None {
+            if val.is_none() {
                 state.exec_state = ExecState::READ(p)
             } else {
                 state.exec_state = ExecState::EXEC
@@ -133,17 +187,23 @@ impl<'a> Cpu<'a> {
         let ports = CpuPorts {
             outports:   write_ports,
             inports:    read_ports,
-            last:       instruction::Port::Up,
+            last:       None,
         };
         Cpu {
             state: Default::default(),
-            ports: ports,
-            executable: executable,
+            ports,
+            executable,
+            range: Default::default(),
         }
     }
 
+    /// Changes the range of values ACC, BAK and written values saturate at
+    pub fn set_word_range(&mut self, range: WordRange) {
+        self.range = range;
+    }
+
     pub fn execute(&mut self) -> bool {
-        if self.executable.len() == 0 {
+        if self.executable.is_empty() {
             return false;
         }
 
@@ -157,19 +217,18 @@ impl<'a> Cpu<'a> {
         let advance_pc = match *self.executable.insn_at(self.pc()) {
             Instruction::NOP => true,
             Instruction::MOV { ref src, ref dst } => {
-                match get_operand(&mut self.state, &mut self.ports, src) {
-                    Some(i) => match dst {
-                            &Operand::Lit(_) => panic!("Cannot store to a literal"),
-                            &Operand::ACC => { self.state.acc = i; true },
-                            &Operand::Port(p) => { self.state.pending_write = Some((p, i)); false },
+                match get_operand(&mut self.state, &mut self.ports, src).map(|i| self.range.clamp(i)) {
+                    Some(i) => match *dst {
+                            Operand::Lit(_) => panic!("Cannot store to a literal"),
+                            Operand::ACC => { self.state.acc = i; true },
+                            Operand::NIL => true,
+                            Operand::Port(p) => { self.state.pending_write = Some((p, i)); false },
                     },
                     None => false
                 }
             },
             Instruction::SWP => {
-                let tmp = self.state.acc;
-                self.state.acc = self.state.bak;
-                self.state.bak = tmp;
+                mem::swap(&mut self.state.acc, &mut self.state.bak);
                 true
             },
             Instruction::SAV => {
@@ -178,21 +237,21 @@ impl<'a> Cpu<'a> {
             },
             Instruction::ADD { ref addend } => {
                 match get_operand(&mut self.state, &mut self.ports, addend) {
-                    Some(i) => { self.state.acc += i; true },
+                    Some(i) => { self.state.acc = self.range.clamp(self.state.acc.saturating_add(i)); true },
                     None => false
                 }
             },
             Instruction::SUB { ref subtrahend } => {
                 match get_operand(&mut self.state, &mut self.ports, subtrahend) {
-                    Some(i) => { self.state.acc -= i; true },
+                    Some(i) => { self.state.acc = self.range.clamp(self.state.acc.saturating_sub(i)); true },
                     None => false
                 }
             },
             Instruction::NEG => {
-                self.state.acc = -self.state.acc;
+                self.state.acc = self.range.clamp(self.state.acc.saturating_neg());
                 true
             },
-            Instruction::J { ref cond, ref dst } => {
+            Instruction::J { ref cond, .. } => {
                 if match *cond {
                     Condition::Unconditional => true,
                     Condition::Ez => self.state.acc == 0,
@@ -200,7 +259,8 @@ impl<'a> Cpu<'a> {
                     Condition::Gz => self.state.acc > 0,
                     Condition::Lz => self.state.acc < 0,
                 } {
-                    self.state.pc = self.executable.label_line(dst) as i32;
+                    self.state.pc = self.executable.jump_target_at(self.pc())
+                        .expect("Jump without a resolved target") as i32;
                     false
                 } else {
                     true
@@ -208,7 +268,12 @@ impl<'a> Cpu<'a> {
             },
             Instruction::JRO { ref dst } => {
                 match get_operand(&mut self.state, &mut self.ports, dst) {
-                    Some(i) => { self.state.pc += i; true },
+                    Some(i) => {
+                        /* JRO saturates at the first and last instructions */
+                        let last = self.executable.len() as i32 - 1;
+                        self.state.pc = self.state.pc.saturating_add(i).clamp(0, last);
+                        false
+                    },
                     None => false
                 }
             },
@@ -239,10 +304,37 @@ impl<'a> Cpu<'a> {
         }
     }
 
+    pub fn acc(&self) -> i32 {
+        self.state.acc
+    }
+
+    pub fn bak(&self) -> i32 {
+        self.state.bak
+    }
+
+    /// Port that LAST refers to, or None if ANY hasn't been used yet
+    pub fn last(&self) -> Option<instruction::Port> {
+        self.ports.last
+    }
+
+    pub fn executable(&self) -> &Executable {
+        &self.executable
+    }
+
     pub fn current_line(&self) -> u32 {
         self.executable.srcline_at(self.pc())
     }
 
+    /// Returns true if the CPU has no code or is waiting on a port
+    pub fn blocked(&self) -> bool {
+        self.executable.is_empty() || self.state.exec_state != ExecState::EXEC
+    }
+
+    /// Returns true if the next instruction to execute has a breakpoint
+    pub fn at_breakpoint(&self) -> bool {
+        !self.executable.is_empty() && self.executable.breakpoint_at(self.pc())
+    }
+
     pub fn exec_state(&self) -> ExecState {
         self.state.exec_state
     }
@@ -251,10 +343,9 @@ impl<'a> Cpu<'a> {
         if advance {
             self.state.pc += 1;
         }
-        /* Handle wrapping at the end and via JRO */
-        self.state.pc %= self.executable.len() as i32;
-        if self.state.pc < 0 {
-            self.state.pc = self.executable.len() as i32 + self.state.pc;
+        /* Fall through from the last instruction wraps to the first */
+        if self.state.pc >= self.executable.len() as i32 {
+            self.state.pc = 0;
         }
     }
 
@@ -265,9 +356,9 @@ impl<'a> Cpu<'a> {
 
 #[cfg(test)]
 mod tests {
-    use super::{Cpu, CpuReadPorts, ExecState};
+    use super::{Cpu, CpuReadPorts, ExecState, WordRange};
     use instruction;
-    use port::{CpuWritePorts, CpuWritePortsReaders, Port, ReadPort};
+    use port::{CpuWritePorts, CpuWritePortsReaders, ReadPort};
     use parse;
 
     #[derive(Default)]
@@ -289,13 +380,8 @@ mod tests {
             Default::default()
         }
 
-        fn cpuports(&self) -> CpuReadPorts {
-            CpuReadPorts {
-                up      : &self.port as &ReadPort,
-                down    : &self.port as &ReadPort,
-                left    : &self.port as &ReadPort,
-                right   : &self.port as &ReadPort,
-            }
+        fn cpuports(&self) -> CpuReadPorts<'_> {
+            CpuReadPorts::new(&self.port, &self.port, &self.port, &self.port)
         }
     }
 
@@ -315,6 +401,19 @@ mod tests {
         cpu.execute();
     }
 
+    #[test]
+    fn test_breakpoint() {
+        let e = parse::parse("NOP\n!NOP").unwrap();
+        let ports = CpuWritePorts::new();
+        let rports = DummyReadPorts::new();
+        let mut cpu = Cpu::new(e, &ports, rports.cpuports());
+        assert!(!cpu.at_breakpoint());
+        cpu.execute();
+        assert!(cpu.at_breakpoint());
+        cpu.execute();
+        assert!(!cpu.at_breakpoint());
+    }
+
     #[test]
     fn test_mov() {
         let e = parse::parse("MOV 10 ACC\nNOP").unwrap();
@@ -350,6 +449,167 @@ mod tests {
         assert_eq!(cpu.state.acc, 10);
     }
 
+    #[test]
+    fn test_saturation() {
+        let e = parse::parse("ADD 999\nADD 999\nNEG\nSUB 999\nADD ACC").unwrap();
+        let ports = CpuWritePorts::new();
+        let rports = DummyReadPorts::new();
+        let mut cpu = Cpu::new(e, &ports, rports.cpuports());
+        cpu.execute();
+        assert_eq!(cpu.acc(), 999);
+        cpu.execute();
+        assert_eq!(cpu.acc(), 999);
+        cpu.execute();
+        assert_eq!(cpu.acc(), -999);
+        cpu.execute();
+        assert_eq!(cpu.acc(), -999);
+        cpu.execute();
+        assert_eq!(cpu.acc(), -999);
+
+        /* Out of range literals are only accepted in sandbox mode */
+        let e = parse::parse_with("MOV 5000 ACC\nMOV -5000 DOWN", parse::Validation::Sandbox).unwrap();
+        let mut cpu = Cpu::new(e, &ports, rports.cpuports());
+        cpu.execute();
+        assert_eq!(cpu.acc(), 999);
+        cpu.execute();
+        cpu.write_cycle();
+        assert_eq!(ports.get_read_port(instruction::Port::Down).read(), Some(-999));
+    }
+
+    #[test]
+    fn test_word_range() {
+        let e = parse::parse_with("ADD 5000\nADD 6000\nNEG\nSUB 9000", parse::Validation::Sandbox).unwrap();
+        let ports = CpuWritePorts::new();
+        let rports = DummyReadPorts::new();
+        let mut cpu = Cpu::new(e, &ports, rports.cpuports());
+        cpu.set_word_range(WordRange::new(-9999, 9999));
+        cpu.execute();
+        assert_eq!(cpu.acc(), 5000);
+        cpu.execute();
+        assert_eq!(cpu.acc(), 9999);
+        cpu.execute();
+        assert_eq!(cpu.acc(), -9999);
+        cpu.execute();
+        assert_eq!(cpu.acc(), -9999);
+
+        let e = parse::parse_with("ADD 2147483647\nADD 1", parse::Validation::Sandbox).unwrap();
+        let mut cpu = Cpu::new(e, &ports, rports.cpuports());
+        cpu.set_word_range(WordRange::new(i32::MIN, i32::MAX));
+        cpu.execute();
+        cpu.execute();
+        assert_eq!(cpu.acc(), i32::MAX);
+
+        let range = WordRange::default();
+        assert_eq!((range.min(), range.max()), (-999, 999));
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_word_range_inverted() {
+        WordRange::new(1, -1);
+    }
+
+    #[test]
+    fn test_nil() {
+        let e = parse::parse("MOV 10 ACC\nADD NIL\nMOV ACC NIL\nSUB NIL\nMOV NIL ACC").unwrap();
+        let ports = CpuWritePorts::new();
+        let rports = DummyReadPorts::new();
+        let mut cpu = Cpu::new(e, &ports, rports.cpuports());
+        for _ in 0..4 {
+            cpu.execute();
+            cpu.write_cycle();
+            assert_eq!(cpu.acc(), 10);
+            assert_eq!(cpu.exec_state(), ExecState::EXEC);
+        }
+        assert!(ports.write_finished());
+        cpu.execute();
+        assert_eq!(cpu.acc(), 0);
+        assert_eq!(cpu.current_line(), 0);
+    }
+
+    #[test]
+    fn test_jump() {
+        let e = parse::parse("TOP: ADD 1\nJLZ TOP\nJGZ NEXT\nNOP\nNEXT: JEZ TOP\nJNZ TOP").unwrap();
+        let ports = CpuWritePorts::new();
+        let rports = DummyReadPorts::new();
+        let mut cpu = Cpu::new(e, &ports, rports.cpuports());
+        cpu.execute();
+        cpu.execute();
+        assert_eq!(cpu.current_line(), 2);
+        cpu.execute();
+        assert_eq!(cpu.current_line(), 4);
+        cpu.execute();
+        assert_eq!(cpu.current_line(), 5);
+        cpu.execute();
+        assert_eq!(cpu.current_line(), 0);
+    }
+
+    #[test]
+    fn test_jro() {
+        let e = parse::parse("JRO 3\nJRO 100\nNOP\nJRO -2\nNOP\nJRO 0").unwrap();
+        let ports = CpuWritePorts::new();
+        let rports = DummyReadPorts::new();
+        let mut cpu = Cpu::new(e, &ports, rports.cpuports());
+        cpu.execute();
+        assert_eq!(cpu.current_line(), 3);
+        cpu.execute();
+        assert_eq!(cpu.current_line(), 1);
+        cpu.execute();
+        /* JRO 100 stops at the last instruction */
+        assert_eq!(cpu.current_line(), 5);
+        cpu.execute();
+        assert_eq!(cpu.current_line(), 5);
+
+        let e = parse::parse("NOP\nNOP\nJRO -100").unwrap();
+        let mut cpu = Cpu::new(e, &ports, rports.cpuports());
+        cpu.execute();
+        cpu.execute();
+        cpu.execute();
+        /* JRO -100 stops at the first instruction */
+        assert_eq!(cpu.current_line(), 0);
+    }
+
+    #[test]
+    fn test_jro_acc() {
+        let e = parse::parse("MOV 3 ACC\nJRO ACC\nNOP\nNOP\nSUB 10\nJRO ACC").unwrap();
+        let ports = CpuWritePorts::new();
+        let rports = DummyReadPorts::new();
+        let mut cpu = Cpu::new(e, &ports, rports.cpuports());
+        cpu.execute();
+        cpu.execute();
+        assert_eq!(cpu.current_line(), 4);
+        cpu.execute();
+        assert_eq!(cpu.acc(), -7);
+        cpu.execute();
+        assert_eq!(cpu.current_line(), 0);
+    }
+
+    #[test]
+    fn test_jro_port() {
+        let e = parse::parse("JRO UP\nNOP\nNOP\nJRO LEFT").unwrap();
+        let ports = CpuWritePorts::new();
+        let inports = CpuWritePorts::new();
+        let rports = CpuWritePortsReaders::from(&inports);
+        let rports = CpuReadPorts::new(rports.up, rports.down, rports.left, rports.right);
+        let mut cpu = Cpu::new(e, &ports, rports);
+
+        /* Blocks until the offset is available */
+        cpu.execute();
+        cpu.write_cycle();
+        assert_eq!(cpu.exec_state(), ExecState::READ(instruction::Port::Up));
+        assert_eq!(cpu.current_line(), 0);
+        inports.write_port(instruction::Port::Up, 3);
+        cpu.execute();
+        cpu.write_cycle();
+        assert_eq!(cpu.exec_state(), ExecState::EXEC);
+        assert_eq!(cpu.current_line(), 3);
+
+        inports.write_port(instruction::Port::Left, -2);
+        cpu.execute();
+        cpu.write_cycle();
+        assert_eq!(cpu.current_line(), 1);
+    }
+
     #[test]
     fn test_port_write() {
         let e = parse::parse("MOV 10 DOWN\nNOP").unwrap();
@@ -399,14 +659,15 @@ mod tests {
     /// Ensures write ANY can be read from any output, but only one output
     #[test]
     fn test_write_any_last() {
-        let e = parse::parse("MOV 10 ANY\n
-                              MOV 20 ANY\n
-                              MOV 30 ANY\n
-                              MOV 40 ANY\n
+        let e = parse::parse("MOV 10 ANY\n\
+                              MOV 20 ANY\n\
+                              MOV 30 ANY\n\
+                              MOV 40 ANY\n\
                               MOV 50 LAST").unwrap();
         let ports = CpuWritePorts::new();
         let rports = DummyReadPorts::new();
         let mut cpu = Cpu::new(e, &ports, rports.cpuports());
+        assert_eq!(cpu.last(), None);
 
         cpu.execute();
         cpu.write_cycle();
@@ -417,6 +678,7 @@ mod tests {
         assert_eq!(ports.get_read_port(instruction::Port::Right).read(), None);
         cpu.execute();
         cpu.write_cycle();
+        assert_eq!(cpu.last(), Some(instruction::Port::Up));
 
         cpu.execute();
         cpu.write_cycle();
@@ -476,12 +738,7 @@ mod tests {
         let ports = CpuWritePorts::new();
         let inports = CpuWritePorts::new();
         let rports = CpuWritePortsReaders::from(&inports);
-        let rports = CpuReadPorts {
-            up:     &rports.up,
-            down:   &rports.down,
-            left:   &rports.left,
-            right:  &rports.right,
-        };
+        let rports = CpuReadPorts::new(rports.up, rports.down, rports.left, rports.right);
 
         let mut cpu = Cpu::new(e, &ports, rports);
 
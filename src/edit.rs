@@ -0,0 +1,247 @@
+use parse::{MAX_LINES, MAX_LINE_LEN};
+
+/// Position in a node's source
+///
+/// Lines and columns count from 0, and columns count characters.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Cursor {
+    pub line:   usize,
+    pub col:    usize,
+}
+
+/// A change to a node's source or a movement of the cursor
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Edit {
+    Insert(char),
+    Newline,
+    /// Delete the character before the cursor
+    Backspace,
+    /// Delete the character under the cursor
+    Delete,
+    Left,
+    Right,
+    Up,
+    Down,
+    Home,
+    End,
+}
+
+/// Editor for the source of one node
+///
+/// Like the game, the editor refuses edits that would take the source over
+/// MAX_LINES lines or a line over MAX_LINE_LEN characters. Source loaded
+/// from a file may already be over the limits, and can still be shortened.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Editor {
+    /// Padded with empty lines so the cursor can move to any line
+    lines:  Vec<String>,
+    cursor: Cursor,
+}
+
+fn char_len(s: &str) -> usize {
+    s.chars().count()
+}
+
+/// Byte offset of a column in a line, which may be its end
+fn byte_offset(s: &str, col: usize) -> usize {
+    s.char_indices().nth(col).map_or(s.len(), |(i, _)| i)
+}
+
+impl Editor {
+    pub fn new(src: &str) -> Self {
+        let mut lines: Vec<String> = src.lines().map(str::to_string).collect();
+        while lines.last().is_some_and(|l| l.is_empty()) {
+            lines.pop();
+        }
+        while lines.len() < MAX_LINES {
+            lines.push(String::new());
+        }
+        Editor { lines, cursor: Cursor::default() }
+    }
+
+    /// The edited source, without trailing empty lines
+    pub fn source(&self) -> String {
+        let len = self.lines.iter().rposition(|l| !l.is_empty()).map_or(0, |i| i + 1);
+        self.lines[..len].join("\n")
+    }
+
+    pub fn cursor(&self) -> Cursor {
+        self.cursor
+    }
+
+    /// Applies an edit, returning whether the source changed
+    pub fn edit(&mut self, edit: Edit) -> bool {
+        let Cursor { line, col } = self.cursor;
+        let len = char_len(&self.lines[line]);
+
+        match edit {
+            Edit::Insert(c) => {
+                if len >= MAX_LINE_LEN {
+                    return false;
+                }
+                let at = byte_offset(&self.lines[line], col);
+                self.lines[line].insert(at, c);
+                self.cursor.col += 1;
+            },
+            Edit::Newline => {
+                /* On the last line there is nowhere for the rest of the line to go */
+                if line + 1 >= self.lines.len() || self.lines.last().is_some_and(|l| !l.is_empty()) {
+                    return false;
+                }
+                let at = byte_offset(&self.lines[line], col);
+                let rest = self.lines[line].split_off(at);
+                self.lines.insert(line + 1, rest);
+                self.lines.pop();
+                self.cursor = Cursor { line: line + 1, col: 0 };
+            },
+            Edit::Backspace if col > 0 => {
+                let at = byte_offset(&self.lines[line], col - 1);
+                self.lines[line].remove(at);
+                self.cursor.col -= 1;
+            },
+            Edit::Backspace => {
+                if line == 0 || !self.join(line - 1) {
+                    return false;
+                }
+            },
+            Edit::Delete if col < len => {
+                let at = byte_offset(&self.lines[line], col);
+                self.lines[line].remove(at);
+            },
+            Edit::Delete => return self.join(line),
+            Edit::Left => {
+                self.cursor.col = col.saturating_sub(1);
+                return false;
+            },
+            Edit::Right => {
+                self.cursor.col = (col + 1).min(len);
+                return false;
+            },
+            Edit::Up => {
+                self.move_to_line(line.saturating_sub(1));
+                return false;
+            },
+            Edit::Down => {
+                self.move_to_line((line + 1).min(self.lines.len() - 1));
+                return false;
+            },
+            Edit::Home => {
+                self.cursor.col = 0;
+                return false;
+            },
+            Edit::End => {
+                self.cursor.col = len;
+                return false;
+            },
+        }
+        true
+    }
+
+    /// Appends the line after `line` to it, leaving the cursor at the join
+    fn join(&mut self, line: usize) -> bool {
+        if line + 1 >= self.lines.len() {
+            return false;
+        }
+        let len = char_len(&self.lines[line]);
+        if len + char_len(&self.lines[line + 1]) > MAX_LINE_LEN {
+            return false;
+        }
+        let next = self.lines.remove(line + 1);
+        self.lines[line].push_str(&next);
+        if self.lines.len() < MAX_LINES {
+            self.lines.push(String::new());
+        }
+        self.cursor = Cursor { line, col: len };
+        true
+    }
+
+    fn move_to_line(&mut self, line: usize) {
+        self.cursor.line = line;
+        self.cursor.col = self.cursor.col.min(char_len(&self.lines[line]));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Cursor, Edit, Editor};
+    use parse::{MAX_LINES, MAX_LINE_LEN};
+
+    fn type_str(e: &mut Editor, s: &str) {
+        for c in s.chars() {
+            e.edit(Edit::Insert(c));
+        }
+    }
+
+    #[test]
+    fn test_editor() {
+        let mut e = Editor::new("MOV UP DOWN");
+        assert_eq!(e.source(), "MOV UP DOWN");
+
+        e.edit(Edit::End);
+        assert!(e.edit(Edit::Newline));
+        type_str(&mut e, "ADD 1");
+        assert_eq!(e.source(), "MOV UP DOWN\nADD 1");
+        assert_eq!(e.cursor(), Cursor { line: 1, col: 5 });
+
+        /* Movement keeps the cursor within the text */
+        assert!(!e.edit(Edit::Up));
+        assert_eq!(e.cursor(), Cursor { line: 0, col: 5 });
+        e.edit(Edit::Down);
+        e.edit(Edit::Down);
+        assert_eq!(e.cursor(), Cursor { line: 2, col: 0 });
+
+        /* Backspace at the start of a line joins it to the previous one */
+        e.edit(Edit::Backspace);
+        e.edit(Edit::Backspace);
+        assert_eq!(e.source(), "MOV UP DOWN\nADD ");
+        e.edit(Edit::Home);
+        assert!(e.edit(Edit::Backspace));
+        assert_eq!(e.source(), "MOV UP DOWNADD ");
+        assert_eq!(e.cursor(), Cursor { line: 0, col: 11 });
+        assert!(e.edit(Edit::Delete));
+        assert_eq!(e.source(), "MOV UP DOWNDD ");
+        e.edit(Edit::Newline);
+        assert_eq!(e.source(), "MOV UP DOWN\nDD ");
+        assert!(!Editor::new("").edit(Edit::Backspace));
+    }
+
+    #[test]
+    fn test_editor_limits() {
+        let mut e = Editor::new("");
+        type_str(&mut e, &"A".repeat(MAX_LINE_LEN + 3));
+        assert_eq!(e.source(), "A".repeat(MAX_LINE_LEN));
+        assert!(!e.edit(Edit::Insert('A')));
+
+        for _ in 1..MAX_LINES {
+            assert!(e.edit(Edit::Newline));
+            e.edit(Edit::Insert('N'));
+        }
+        assert!(!e.edit(Edit::Newline));
+        assert_eq!(e.source().lines().count(), MAX_LINES);
+
+        /* Nor can a line be added after the last one */
+        let mut e = Editor::new("");
+        for _ in 1..MAX_LINES {
+            e.edit(Edit::Down);
+        }
+        assert!(!e.edit(Edit::Newline));
+        assert_eq!(e.cursor(), Cursor { line: MAX_LINES - 1, col: 0 });
+        assert!(e.edit(Edit::Insert('A')));
+        assert_eq!(e.source(), format!("{}A", "\n".repeat(MAX_LINES - 1)));
+
+        /* Lines can't be joined past the limit */
+        let mut e = Editor::new("ADD 10\nMOV LEFT DOWN");
+        e.edit(Edit::End);
+        assert!(!e.edit(Edit::Delete));
+        e.edit(Edit::Backspace);
+        assert!(e.edit(Edit::Delete));
+        assert_eq!(e.source(), "ADD 1MOV LEFT DOWN");
+
+        /* Source over the limits can be shortened */
+        let long = "L".repeat(MAX_LINE_LEN + 1);
+        let mut e = Editor::new(&long);
+        e.edit(Edit::End);
+        assert!(e.edit(Edit::Backspace));
+        assert_eq!(e.source(), "L".repeat(MAX_LINE_LEN));
+    }
+}
@@ -0,0 +1,601 @@
+use std::error;
+use std::fmt;
+use cpu::{Cpu, CpuReadPorts, ExecState, WordRange};
+use image::{Image, ImageConsole};
+use instruction;
+use parse::{Executable, ParseError};
+use port::{CpuWritePorts, NullReadPort, ReadPort};
+use save::Solution;
+use stack::Stack;
+use stream::{InputStream, OutputStatus, OutputStream};
+
+/// Size of the grid in the game
+pub const DEFAULT_WIDTH: usize = 4;
+pub const DEFAULT_HEIGHT: usize = 3;
+
+/// Output ports for every node in a grid
+///
+/// Nodes hold references to their neighbours' ports, so this is created
+/// separately and must outlive the Grid built on top of it.
+pub struct GridPorts {
+    width:  usize,
+    height: usize,
+    ports:  Vec<CpuWritePorts>,
+    /// Input streams above the top row, by column
+    inputs: Vec<Option<InputStream>>,
+}
+
+impl Default for GridPorts {
+    fn default() -> Self {
+        Self::new(DEFAULT_WIDTH, DEFAULT_HEIGHT)
+    }
+}
+
+impl GridPorts {
+    pub fn new(width: usize, height: usize) -> Self {
+        GridPorts {
+            width,
+            height,
+            ports:  (0..width * height).map(|_| CpuWritePorts::new()).collect(),
+            inputs: (0..width).map(|_| None).collect(),
+        }
+    }
+
+    /// Places an input stream above the top node of a column
+    pub fn set_input(&mut self, column: usize, input: InputStream) {
+        self.inputs[column] = Some(input);
+    }
+
+    /// Input stream above a column, if there is one
+    pub fn input(&self, column: usize) -> Option<&InputStream> {
+        self.inputs[column].as_ref()
+    }
+
+    /// Returns true once every input stream has been read completely
+    pub fn inputs_exhausted(&self) -> bool {
+        self.inputs.iter().flatten().all(|i| i.is_exhausted())
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    /// Output ports of the node at (x, y)
+    pub fn ports_at(&self, x: usize, y: usize) -> &CpuWritePorts {
+        assert!(x < self.width && y < self.height);
+        &self.ports[y * self.width + x]
+    }
+
+    /// Returns the port a node reads from when reading in direction `dir`
+    ///
+    /// This is the neighbour's output port facing back toward the node, the
+    /// input stream above the top row, or a port that never produces a value
+    /// at the edge of the grid.
+    fn neighbour_reader(&self, x: usize, y: usize, dir: instruction::Port) -> Box<dyn ReadPort + '_> {
+        let (nx, ny, facing) = match dir {
+            instruction::Port::Up =>    (x as isize, y as isize - 1, instruction::Port::Down),
+            instruction::Port::Down =>  (x as isize, y as isize + 1, instruction::Port::Up),
+            instruction::Port::Left =>  (x as isize - 1, y as isize, instruction::Port::Right),
+            instruction::Port::Right => (x as isize + 1, y as isize, instruction::Port::Left),
+            _ => panic!("Invalid port")
+        };
+
+        if ny < 0 {
+            match self.inputs[x] {
+                Some(ref input) => Box::new(input),
+                None => Box::new(NullReadPort),
+            }
+        } else if nx < 0 || nx as usize >= self.width || ny as usize >= self.height {
+            Box::new(NullReadPort)
+        } else {
+            Box::new(self.ports_at(nx as usize, ny as usize).get_read_port(facing))
+        }
+    }
+
+    /// Builds the input side of the node at (x, y) from its neighbours' outputs
+    fn read_ports(&self, x: usize, y: usize) -> CpuReadPorts<'_> {
+        CpuReadPorts::new(self.neighbour_reader(x, y, instruction::Port::Up),
+                          self.neighbour_reader(x, y, instruction::Port::Down),
+                          self.neighbour_reader(x, y, instruction::Port::Left),
+                          self.neighbour_reader(x, y, instruction::Port::Right))
+    }
+}
+
+/// Kinds of node that can fill a slot of the grid
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum NodeType {
+    /// T21 execution node
+    Compute,
+    /// T30 stack memory node
+    Stack,
+    /// Corrupted node that never runs code or communicates. Neighbours
+    /// reading from or writing to it block forever.
+    Damaged,
+}
+
+pub enum Node<'a> {
+    Compute(Cpu<'a>),
+    Stack(Stack<'a>),
+    Damaged,
+}
+
+impl<'a> Node<'a> {
+    pub fn node_type(&self) -> NodeType {
+        match *self {
+            Node::Compute(_) => NodeType::Compute,
+            Node::Stack(_) => NodeType::Stack,
+            Node::Damaged => NodeType::Damaged,
+        }
+    }
+
+    fn execute(&mut self) {
+        match *self {
+            Node::Compute(ref mut cpu) => { cpu.execute(); },
+            Node::Stack(ref mut stack) => stack.execute(),
+            Node::Damaged => (),
+        }
+    }
+
+    fn write_cycle(&mut self) {
+        match *self {
+            Node::Compute(ref mut cpu) => cpu.write_cycle(),
+            Node::Stack(ref mut stack) => stack.write_cycle(),
+            Node::Damaged => (),
+        }
+    }
+
+    /// State that changes whenever the node makes progress, or None if the
+    /// node can run without its neighbours
+    fn wait_state(&self) -> Option<(ExecState, usize)> {
+        match *self {
+            Node::Compute(ref cpu) if cpu.blocked() => Some((cpu.exec_state(), 0)),
+            Node::Compute(_) => None,
+            Node::Stack(ref stack) => Some((ExecState::EXEC, stack.len())),
+            Node::Damaged => Some((ExecState::EXEC, 0)),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum GridError {
+    /// A node that can't run code was given a program
+    CodeInNode { x: usize, y: usize, node: NodeType },
+    /// A solution has code for more compute nodes than the layout has
+    ExtraNode { node: usize },
+    /// A solution's node failed to parse
+    Parse { node: usize, error: ParseError },
+}
+
+impl fmt::Display for GridError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        match *self {
+            GridError::CodeInNode { x, y, node } =>
+                write!(f, "Node at {},{} is a {:?} node and can't hold code", x, y, node),
+            GridError::ExtraNode { node } =>
+                write!(f, "Code for node {} which isn't a compute node", node),
+            GridError::Parse { node, ref error } =>
+                write!(f, "Node {}: {}", node, error),
+        }
+    }
+}
+
+impl error::Error for GridError {}
+
+/// A rectangle of nodes, each connected to its neighbours
+pub struct Grid<'a> {
+    ports:      &'a GridPorts,
+    nodes:      Vec<Node<'a>>,
+    /// Output streams below the bottom row, by column
+    outputs:    Vec<Option<OutputStream<'a>>>,
+    /// Image consoles below the bottom row, by column
+    images:     Vec<Option<ImageConsole<'a>>>,
+    cycle:      u64,
+    deadlocked: bool,
+}
+
+impl<'a> Grid<'a> {
+    /// Creates a grid of CPUs running one executable per node
+    ///
+    /// Executables are given in row-major order, starting at the top left.
+    pub fn new(ports: &'a GridPorts, executables: Vec<Executable>) -> Grid<'a> {
+        let layout = vec![NodeType::Compute; executables.len()];
+        Self::with_layout(ports, &layout, executables).expect("All nodes are compute nodes")
+    }
+
+    /// Creates a grid with the given type of node in each slot
+    ///
+    /// Layout and executables are given in row-major order. Only compute
+    /// nodes may have a non-empty executable.
+    pub fn with_layout(ports: &'a GridPorts, layout: &[NodeType], executables: Vec<Executable>)
+        -> Result<Grid<'a>, GridError> {
+        assert_eq!(layout.len(), ports.width() * ports.height());
+        assert_eq!(executables.len(), layout.len());
+
+        let mut nodes = Vec::with_capacity(layout.len());
+        for (i, (&node, e)) in layout.iter().zip(executables).enumerate() {
+            let (x, y) = (i % ports.width(), i / ports.width());
+            if node != NodeType::Compute && !e.is_empty() {
+                return Err(GridError::CodeInNode { x, y, node });
+            }
+            nodes.push(match node {
+                NodeType::Compute => Node::Compute(Cpu::new(e, ports.ports_at(x, y), ports.read_ports(x, y))),
+                NodeType::Stack => Node::Stack(Stack::new(ports.ports_at(x, y), ports.read_ports(x, y))),
+                NodeType::Damaged => Node::Damaged,
+            });
+        }
+
+        Ok(Grid {
+            ports,
+            nodes,
+            outputs:    (0..ports.width()).map(|_| None).collect(),
+            images:     (0..ports.width()).map(|_| None).collect(),
+            cycle:      0,
+            deadlocked: false,
+        })
+    }
+
+    /// Creates a grid running a solution in the game's save format
+    ///
+    /// Like the game, solution nodes are numbered in row-major order counting
+    /// only compute nodes. Code for any other node is rejected.
+    pub fn from_solution(ports: &'a GridPorts, layout: &[NodeType], solution: &Solution)
+        -> Result<Grid<'a>, GridError> {
+        let mut programs = solution.parse()
+            .map_err(|(node, error)| GridError::Parse { node, error })?
+            .into_iter();
+
+        let executables = layout.iter().map(|&node| match node {
+            NodeType::Compute => programs.next().unwrap_or_default(),
+            _ => Executable::default(),
+        }).collect();
+
+        let numcompute = layout.iter().filter(|&&n| n == NodeType::Compute).count();
+        if let Some(i) = programs.position(|e| !e.is_empty()) {
+            return Err(GridError::ExtraNode { node: numcompute + i });
+        }
+
+        Self::with_layout(ports, layout, executables)
+    }
+
+    /// Solution node number of each slot of a layout, as from_solution()
+    /// numbers them, or None for slots that aren't compute nodes
+    pub fn solution_nodes(layout: &[NodeType]) -> Vec<Option<usize>> {
+        let mut next = 0;
+        layout.iter().map(|&node| match node {
+            NodeType::Compute => { next += 1; Some(next - 1) },
+            _ => None,
+        }).collect()
+    }
+
+    pub fn width(&self) -> usize {
+        self.ports.width()
+    }
+
+    pub fn height(&self) -> usize {
+        self.ports.height()
+    }
+
+    /// Output ports of every node, holding the values waiting to be read
+    pub fn ports(&self) -> &'a GridPorts {
+        self.ports
+    }
+
+    pub fn node_at(&self, x: usize, y: usize) -> &Node<'a> {
+        assert!(x < self.width() && y < self.height());
+        &self.nodes[y * self.width() + x]
+    }
+
+    /// Returns the CPU at (x, y), or None if it's another kind of node
+    pub fn cpu_at(&self, x: usize, y: usize) -> Option<&Cpu<'a>> {
+        match *self.node_at(x, y) {
+            Node::Compute(ref cpu) => Some(cpu),
+            _ => None,
+        }
+    }
+
+    /// Returns the stack at (x, y), or None if it's another kind of node
+    pub fn stack_at(&self, x: usize, y: usize) -> Option<&Stack<'a>> {
+        match *self.node_at(x, y) {
+            Node::Stack(ref stack) => Some(stack),
+            _ => None,
+        }
+    }
+
+    /// Places an output stream below the bottom node of a column
+    ///
+    /// The bottom node writes to it with DOWN. This replaces any image
+    /// console in the column.
+    pub fn set_output(&mut self, column: usize, expected: Vec<i32>) {
+        let port = self.ports.ports_at(column, self.height() - 1).get_read_port(instruction::Port::Down);
+        self.outputs[column] = Some(OutputStream::new(port, expected));
+        self.images[column] = None;
+    }
+
+    /// Places an image console below the bottom node of a column
+    ///
+    /// This replaces any output stream in the column.
+    pub fn set_image(&mut self, column: usize, expected: Image) {
+        let port = self.ports.ports_at(column, self.height() - 1).get_read_port(instruction::Port::Down);
+        self.images[column] = Some(ImageConsole::new(port, expected));
+        self.outputs[column] = None;
+    }
+
+    /// Image console below a column, if there is one
+    pub fn image(&self, column: usize) -> Option<&ImageConsole<'a>> {
+        self.images[column].as_ref()
+    }
+
+    /// Output stream below a column, if there is one
+    pub fn output(&self, column: usize) -> Option<&OutputStream<'a>> {
+        self.outputs[column].as_ref()
+    }
+
+    /// Combined status of every output stream and image console
+    pub fn output_status(&self) -> OutputStatus {
+        let status = self.outputs.iter().flatten()
+            .fold(OutputStatus::Pass, |status, o| status.combine(o.status()));
+        self.images.iter().flatten()
+            .fold(status, |status, i| status.combine(i.status()))
+    }
+
+    /// Changes the range of values every CPU saturates at
+    pub fn set_word_range(&mut self, range: WordRange) {
+        for node in self.nodes.iter_mut() {
+            if let Node::Compute(ref mut cpu) = *node {
+                cpu.set_word_range(range);
+            }
+        }
+    }
+
+    /// Positions of the CPUs whose next instruction has a breakpoint
+    pub fn breakpoints_reached(&self) -> Vec<(usize, usize)> {
+        (0..self.nodes.len())
+            .map(|i| (i % self.width(), i / self.width()))
+            .filter(|&(x, y)| self.cpu_at(x, y).is_some_and(|cpu| cpu.at_breakpoint()))
+            .collect()
+    }
+
+    /// Number of cycles executed so far
+    pub fn cycle(&self) -> u64 {
+        self.cycle
+    }
+
+    /// Returns true if the last cycle changed nothing while every CPU was
+    /// waiting on a port, so no node can make progress again
+    pub fn deadlocked(&self) -> bool {
+        self.deadlocked
+    }
+
+    fn wait_states(&self) -> Option<Vec<(ExecState, usize)>> {
+        self.nodes.iter().map(Node::wait_state).collect()
+    }
+
+    /// Runs one cycle on every node
+    ///
+    /// All nodes execute before any node's writes are processed, so the
+    /// result does not depend on the order in which nodes are visited.
+    pub fn step(&mut self) {
+        let before = self.wait_states();
+        for node in self.nodes.iter_mut() {
+            node.execute();
+        }
+        for output in self.outputs.iter_mut().flatten() {
+            output.read_cycle(self.cycle + 1);
+        }
+        for image in self.images.iter_mut().flatten() {
+            image.read_cycle();
+        }
+        for node in self.nodes.iter_mut() {
+            node.write_cycle();
+        }
+        self.cycle += 1;
+        self.deadlocked = before.is_some() && self.wait_states() == before;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Grid, GridError, GridPorts, NodeType};
+    use image::{Color, Image};
+    use save::Solution;
+    use std::str::FromStr;
+    use stream::{InputStream, OutputStatus};
+    use cpu::ExecState;
+    use instruction;
+    use parse;
+
+    fn grid<'a>(ports: &'a GridPorts, srcs: &[&str]) -> Grid<'a> {
+        Grid::new(ports, srcs.iter().map(|s| parse::parse(s).unwrap()).collect())
+    }
+
+    #[test]
+    fn test_grid_pass_around() {
+        let ports = GridPorts::new(2, 2);
+        let mut grid = grid(&ports, &["MOV 5 RIGHT",   "MOV LEFT DOWN",
+                                      "MOV RIGHT ACC", "MOV UP LEFT"]);
+        for _ in 0..12 {
+            grid.step();
+        }
+        assert_eq!(grid.cycle(), 12);
+        assert_eq!(grid.cpu_at(0, 1).unwrap().acc(), 5);
+    }
+
+    #[test]
+    fn test_grid_edges_block() {
+        let ports = GridPorts::new(1, 1);
+        let mut grid = grid(&ports, &["MOV UP ACC\nMOV ACC LEFT"]);
+        for _ in 0..4 {
+            grid.step();
+            assert_eq!(grid.cpu_at(0, 0).unwrap().exec_state(), ExecState::READ(instruction::Port::Up));
+        }
+        assert!(grid.deadlocked());
+
+        /* A CPU that is still running isn't deadlocked, even if it loops */
+        let mut looping = Grid::new(&ports, vec![parse::parse("L: JMP L").unwrap()]);
+        looping.step();
+        looping.step();
+        assert!(!looping.deadlocked());
+    }
+
+    #[test]
+    fn test_grid_default_size() {
+        let ports = GridPorts::default();
+        let grid = grid(&ports, &[""; 12]);
+        assert_eq!(grid.width(), 4);
+        assert_eq!(grid.height(), 3);
+    }
+
+    #[test]
+    fn test_grid_stack() {
+        let ports = GridPorts::new(3, 1);
+        let layout = [NodeType::Compute, NodeType::Stack, NodeType::Compute];
+        let srcs = ["MOV 1 RIGHT\nMOV 2 RIGHT\nMOV 3 RIGHT\nL: JMP L",
+                    "",
+                    "MOV 20 ACC\nW: SUB 1\nJGZ W\nMOV LEFT ACC\nSAV\nMOV LEFT ACC\nL: JMP L"];
+        let mut grid = Grid::with_layout(&ports, &layout,
+                                         srcs.iter().map(|s| parse::parse(s).unwrap()).collect()).unwrap();
+        assert!(grid.cpu_at(1, 0).is_none());
+
+        for _ in 0..100 {
+            grid.step();
+        }
+        /* Values come back out in reverse order */
+        assert_eq!(grid.cpu_at(2, 0).unwrap().bak(), 3);
+        assert_eq!(grid.cpu_at(2, 0).unwrap().acc(), 2);
+        assert_eq!(grid.stack_at(1, 0).unwrap().values(), &[1]);
+    }
+
+    #[test]
+    fn test_grid_code_in_stack() {
+        let ports = GridPorts::new(2, 1);
+        let layout = [NodeType::Compute, NodeType::Stack];
+        let err = Grid::with_layout(&ports, &layout,
+                                    vec![parse::parse("").unwrap(), parse::parse("NOP").unwrap()]).err();
+        assert_eq!(err, Some(GridError::CodeInNode { x: 1, y: 0, node: NodeType::Stack }));
+    }
+
+    #[test]
+    fn test_grid_damaged() {
+        let ports = GridPorts::new(3, 1);
+        let layout = [NodeType::Compute, NodeType::Damaged, NodeType::Compute];
+        let srcs = ["MOV 1 RIGHT", "", "MOV LEFT ACC"];
+        let mut grid = Grid::with_layout(&ports, &layout,
+                                         srcs.iter().map(|s| parse::parse(s).unwrap()).collect()).unwrap();
+        for _ in 0..10 {
+            grid.step();
+        }
+        /* Neither the write nor the read ever completes */
+        assert_eq!(grid.cpu_at(0, 0).unwrap().exec_state(), ExecState::WRITE(instruction::Port::Right));
+        assert_eq!(grid.cpu_at(2, 0).unwrap().exec_state(), ExecState::READ(instruction::Port::Left));
+        assert!(grid.cpu_at(1, 0).is_none());
+
+        let err = Grid::with_layout(&ports, &layout,
+                                    srcs.iter().map(|_| parse::parse("NOP").unwrap()).collect()).err();
+        assert_eq!(err, Some(GridError::CodeInNode { x: 1, y: 0, node: NodeType::Damaged }));
+    }
+
+    #[test]
+    fn test_grid_from_solution() {
+        let ports = GridPorts::new(2, 2);
+        let layout = [NodeType::Compute, NodeType::Damaged,
+                      NodeType::Stack,   NodeType::Compute];
+
+        /* Node numbers skip non-compute nodes */
+        let s = Solution::from_str("@0\nMOV 1 ACC\n\n@1\nMOV 2 ACC\n\n").unwrap();
+        let mut grid = Grid::from_solution(&ports, &layout, &s).unwrap();
+        grid.step();
+        assert_eq!(grid.cpu_at(0, 0).unwrap().acc(), 1);
+        assert_eq!(grid.cpu_at(1, 1).unwrap().acc(), 2);
+        assert_eq!(Grid::solution_nodes(&layout), vec![Some(0), None, None, Some(1)]);
+
+        /* Missing nodes are empty, extra empty nodes are ignored */
+        assert!(Grid::from_solution(&ports, &layout, &Solution::from_str("@0\nNOP").unwrap()).is_ok());
+        assert!(Grid::from_solution(&ports, &layout, &Solution::from_str("@3\n").unwrap()).is_ok());
+
+        let err = Grid::from_solution(&ports, &layout, &Solution::from_str("@3\nNOP").unwrap()).err();
+        assert_eq!(err, Some(GridError::ExtraNode { node: 3 }));
+        match Grid::from_solution(&ports, &layout, &Solution::from_str("@1\nBOGUS").unwrap()).err() {
+            Some(GridError::Parse { node: 1, .. }) => (),
+            _ => panic!("Expected a parse error"),
+        }
+    }
+
+    #[test]
+    fn test_grid_breakpoints() {
+        let ports = GridPorts::new(2, 1);
+        let s = Solution::from_str("@0\nNOP\n!NOP\n\n@1\n!MOV LEFT ACC\n").unwrap();
+        let mut grid = Grid::from_solution(&ports, &[NodeType::Compute; 2], &s).unwrap();
+        assert_eq!(grid.breakpoints_reached(), vec![(1, 0)]);
+
+        /* The right node stays at its breakpoint while blocked */
+        grid.step();
+        assert_eq!(grid.breakpoints_reached(), vec![(0, 0), (1, 0)]);
+        grid.step();
+        assert_eq!(grid.breakpoints_reached(), vec![(1, 0)]);
+    }
+
+    #[test]
+    fn test_grid_input() {
+        let mut ports = GridPorts::new(2, 2);
+        ports.set_input(1, InputStream::new(vec![3, 4, 5]));
+        let mut grid = grid(&ports, &["", "MOV UP DOWN",
+                                      "", "ADD UP"]);
+        assert!(!ports.inputs_exhausted());
+        for _ in 0..20 {
+            grid.step();
+        }
+        assert!(ports.inputs_exhausted());
+        assert_eq!(ports.input(1).unwrap().position(), 3);
+        assert!(ports.input(0).is_none());
+        assert_eq!(grid.cpu_at(1, 1).unwrap().acc(), 12);
+    }
+
+    #[test]
+    fn test_grid_output() {
+        let mut ports = GridPorts::new(2, 1);
+        ports.set_input(0, InputStream::new(vec![1, 2, 3]));
+        let mut grid = grid(&ports, &["MOV UP ACC\nADD ACC\nMOV ACC RIGHT", "MOV LEFT DOWN"]);
+        grid.set_output(1, vec![2, 4, 6]);
+        assert!(grid.output(0).is_none());
+        assert_eq!(grid.output_status(), OutputStatus::Incomplete);
+
+        let mut cycles = 0;
+        while grid.output_status() == OutputStatus::Incomplete && cycles < 100 {
+            grid.step();
+            cycles += 1;
+        }
+        assert_eq!(grid.output_status(), OutputStatus::Pass);
+        let received = grid.output(1).unwrap().received();
+        assert_eq!(received.last().unwrap().0, grid.cycle());
+        assert!(received[0].0 < received[1].0);
+    }
+
+    #[test]
+    fn test_grid_output_fail() {
+        let ports = GridPorts::new(1, 1);
+        let mut grid = grid(&ports, &["MOV 2 DOWN"]);
+        grid.set_output(0, vec![2, 5]);
+        for _ in 0..20 {
+            grid.step();
+        }
+        assert_eq!(grid.output_status(), OutputStatus::Fail { index: 1 });
+    }
+
+    #[test]
+    fn test_grid_image() {
+        let ports = GridPorts::new(1, 1);
+        let mut grid = grid(&ports, &["MOV 1 DOWN\nMOV 0 DOWN\nMOV 3 DOWN\nMOV 2 DOWN\nMOV -1 DOWN\nL: JMP L"]);
+        grid.set_image(0, Image::from_str(" #+").unwrap());
+        assert!(grid.output(0).is_none());
+        assert_eq!(grid.output_status(), OutputStatus::Incomplete);
+        for _ in 0..20 {
+            grid.step();
+        }
+        assert_eq!(grid.output_status(), OutputStatus::Pass);
+        assert_eq!(grid.image(0).unwrap().image().get(2, 0), Color::LightGrey);
+    }
+}
@@ -1,29 +1,44 @@
 extern crate ncurses;
 
+use std::env;
+use std::fmt;
+use std::fs;
 use std::option::Option;
 use std::iter::FromIterator;
+use std::str::FromStr;
 use self::ncurses::*;
-use cpu::ExecState;
+use cpu::{Cpu, ExecState};
+use edit::{Cursor, Edit, Editor};
+use grid::{Grid, GridPorts, Node, NodeType, DEFAULT_HEIGHT, DEFAULT_WIDTH};
+use image::{IMAGE_HEIGHT, IMAGE_WIDTH};
 use instruction;
+use parse;
+use parse::ParseError;
+use puzzle::{Expected, Puzzle, PuzzleOutput};
+use save::Solution;
+use stack::Stack;
+use stream::OutputStatus;
 
 struct CodeWin {
     wtext:  WINDOW,
     lines:  Vec<String>,
     line:   Option<u8>,
+    /// Parse errors, which are marked where they occur
+    errors: Vec<ParseError>,
 }
 
 impl CodeWin {
     fn new(win: WINDOW, s: &str) -> Self {
         let wtext = derwin(win, TEXT_LINES, TEXT_COLS, 0, 0);
-        wbkgd(wtext, '?' as u64);
+        wbkgd(wtext, ' ' as u64);
 
         let mut codewin = CodeWin {
-            wtext: wtext,
-            lines: Self::code_vec(s),
-            line:  None,
+            wtext,
+            lines:  Self::code_vec(s),
+            line:   None,
+            errors: Self::parse_errors(s),
         };
 
-        codewin.set_line(Some(1));
         codewin.draw_all_lines();
         codewin
     }
@@ -32,13 +47,32 @@ impl CodeWin {
         s.lines().take(TEXT_LINES as usize).map(str::to_string).collect()
     }
 
+    fn parse_errors(s: &str) -> Vec<ParseError> {
+        parse::parse_all(s).err().unwrap_or_default()
+    }
+
     fn set_code(&mut self, s: &str) {
         self.lines = Self::code_vec(s);
+        self.errors = Self::parse_errors(s);
+        self.draw_all_lines();
+    }
+
+    /// Shows text that isn't code, so isn't checked for errors
+    fn set_text(&mut self, s: &str) {
+        self.lines = Self::code_vec(s);
+        self.errors.clear();
         self.draw_all_lines();
     }
 
+    /// Whether a character is part of an error, or on a line with an error at no particular place
+    fn in_error(&self, line: u8, col: usize) -> bool {
+        self.errors.iter().any(|e| {
+            e.line == u32::from(line) && (e.cols.contains(&col) || e.cols.is_empty())
+        })
+    }
+
     fn draw_all_lines(&mut self) {
-        for i in (0..TEXT_LINES as u8) {
+        for i in 0..TEXT_LINES as u8 {
             self.draw_line(i);
         }
     }
@@ -59,8 +93,14 @@ impl CodeWin {
         wclrtoeol(self.wtext);
         wmove(self.wtext, line, 0);
         if let Some(s) = self.lines.get(line as usize) {
-            mvwprintw(self.wtext, line, 0, s);
-            let pad: usize = TEXT_COLS as usize - s.len();
+            wmove(self.wtext, line, 0);
+            for (col, c) in s.char_indices().take(TEXT_COLS as usize) {
+                let error = if self.in_error(line as u8, col) { A_UNDERLINE() | A_BOLD() } else { 0 };
+                wattr_on(self.wtext, error);
+                waddstr(self.wtext, c.encode_utf8(&mut [0; 4]));
+                wattr_off(self.wtext, error);
+            }
+            let pad: usize = (TEXT_COLS as usize).saturating_sub(s.chars().count());
             wprintw(self.wtext, &String::from_iter([' '].iter().cloned().cycle().take(pad)));
         }
         wattr_off(self.wtext, A_STANDOUT());
@@ -80,6 +120,12 @@ impl CodeWin {
             self.draw_line(new);
         }
     }
+
+    /// Moves the terminal cursor to a position in the code
+    fn show_cursor(&mut self, cursor: Cursor) {
+        wmove(self.wtext, cursor.line as i32, cursor.col as i32);
+        wrefresh(self.wtext);
+    }
 }
 
 impl Drop for CodeWin {
@@ -95,15 +141,16 @@ struct CpuWin {
     codewin:    CodeWin,
 }
 
-const SIDEBAR_CELL_HEIGHT: i32 = 2;
+/* The code area fits the longest program the game allows */
+const TEXT_LINES: i32 = parse::MAX_LINES as i32;
+const TEXT_COLS: i32 = parse::MAX_LINE_LEN as i32;
+
+const SIDEBAR_CELL_HEIGHT: i32 = 3;
 const SIDEBAR_WIDTH: i32 = 6;
 const CPUWIN_HEIGHT: i32 = (SIDEBAR_CELL_HEIGHT + 1) * 4 + 1;
-const CPUWIN_WIDTH: i32 = CPUWIN_HEIGHT*2 + SIDEBAR_WIDTH;
+const CPUWIN_WIDTH: i32 = TEXT_COLS + SIDEBAR_WIDTH + 3;
 const SIDEBAR_X: i32 = CPUWIN_WIDTH - 1 - SIDEBAR_WIDTH - 1;
 
-const TEXT_LINES: i32 = CPUWIN_HEIGHT - 2;
-const TEXT_COLS: i32 = CPUWIN_WIDTH - SIDEBAR_WIDTH - 3;
-
 impl CpuWin {
     fn new(posx: i32, posy: i32) -> CpuWin {
 
@@ -120,10 +167,10 @@ impl CpuWin {
         mvwaddch(win, CPUWIN_HEIGHT-1, SIDEBAR_X, ACS_BTEE());
 
         let mut cpuwin = CpuWin {
-            win:        win,
-            winner:     winner,
-            wsidebar:   wsidebar,
-            codewin:    CodeWin::new(winner, "line1\nline2\n\nline4"),
+            win,
+            winner,
+            wsidebar,
+            codewin:    CodeWin::new(winner, ""),
         };
         cpuwin.cell_label(0, "ACC");
         cpuwin.cell_label(1, "BAK");
@@ -134,7 +181,7 @@ impl CpuWin {
         cpuwin.cell_divider(2);
         cpuwin.cell_divider(3);
 
-        cpuwin.set_values(0, 10, None, ExecState::EXEC);
+        cpuwin.set_values(0, 0, None, ExecState::EXEC);
 
         cpuwin
     }
@@ -144,7 +191,9 @@ impl CpuWin {
     }
 
     fn cell_val(&mut self, cell: i32, l: &str) {
-        mvwprintw(self.wsidebar, Self::cell_y(cell)+1, 1, l);
+        /* Pad to overwrite any longer previous value */
+        let l = format!("{:^1$}", l, SIDEBAR_WIDTH as usize - 1);
+        mvwprintw(self.wsidebar, Self::cell_y(cell)+1, 0, &l);
     }
 
     fn cell_divider(&mut self, cell: i32) {
@@ -162,14 +211,15 @@ impl CpuWin {
     }
 
     fn set_values(&mut self, acc: i32, bak: i32, last: Option<instruction::Port>, mode: ExecState) {
-        let acc = format!("{:^4}", acc);
-        let bak = format!("{:^4}", bak);
+        let acc = acc.to_string();
+        let bak = bak.to_string();
         let last = last.map_or_else(|| "N/A".to_string(), |p| p.to_string());
         let mode = mode.to_string();
         self.cell_val(0, &acc);
         self.cell_val(1, &bak);
         self.cell_val(2, &last);
         self.cell_val(3, &mode);
+        wrefresh(self.wsidebar);
     }
 
     fn set_code(&mut self, s: &str) {
@@ -179,6 +229,50 @@ impl CpuWin {
     fn set_line(&mut self, newline: Option<u8>) {
         self.codewin.set_line(newline)
     }
+
+    /// Relabels the window of a node that doesn't run code
+    fn set_node_type(&mut self, node: NodeType) {
+        match node {
+            NodeType::Compute => return,
+            NodeType::Stack => {
+                werase(self.wsidebar);
+                self.cell_label(0, "STACK");
+                self.codewin.set_text("");
+            },
+            NodeType::Damaged => {
+                werase(self.wsidebar);
+                self.codewin.set_text(&format!("{}{:^3$}\n{:^3$}", "\n".repeat(6), "COMMUNICATION",
+                                               "FAILURE", TEXT_COLS as usize));
+            },
+        }
+        wrefresh(self.wsidebar);
+    }
+
+    /// Shows the values on a stack, the top one last
+    fn update_stack(&mut self, stack: &Stack) {
+        let values: Vec<String> = stack.values().iter().map(|v| v.to_string()).collect();
+        self.codewin.set_text(&values.join("\n"));
+        self.cell_val(0, &stack.len().to_string());
+        wrefresh(self.wsidebar);
+    }
+
+    fn errors(&self) -> &[ParseError] {
+        &self.codewin.errors
+    }
+
+    fn show_cursor(&mut self, cursor: Cursor) {
+        self.codewin.show_cursor(cursor)
+    }
+
+    /// Shows the current registers and line of a running CPU
+    fn update(&mut self, cpu: &Cpu) {
+        self.set_values(cpu.acc(), cpu.bak(), cpu.last(), cpu.exec_state());
+        if cpu.executable().is_empty() {
+            self.set_line(None);
+        } else {
+            self.set_line(Some(cpu.current_line() as u8));
+        }
+    }
 }
 
 impl Drop for CpuWin {
@@ -191,48 +285,649 @@ impl Drop for CpuWin {
     }
 }
 
-fn create_cpu_wins() -> Vec<Vec<CpuWin>> {
-    let left_margin = 10;
-    let inner_margin = 4;
+/* Space around and between node windows */
+const LEFT_MARGIN: i32 = 10;
+const TOP_MARGIN: i32 = LEFT_MARGIN / 2;
+const INNER_MARGIN: i32 = 4;
+
+/// Screen column of the left edge of a node window, with the grid `left` columns from the edge
+fn node_x(left: i32, x: i32) -> i32 {
+    x*(CPUWIN_WIDTH + INNER_MARGIN) + left
+}
+
+/// Screen line of the top edge of a node window
+fn node_y(y: i32) -> i32 {
+    y*(CPUWIN_HEIGHT + INNER_MARGIN/2) + TOP_MARGIN
+}
 
-    let mut cpuwins: Vec<Vec<CpuWin>> = (0..4).map(|x| {
-        (0..3).map(|y| (x, y)).map(|(x, y)| {
-            CpuWin::new(x*(CPUWIN_WIDTH + inner_margin) + left_margin,
-                        y*(CPUWIN_HEIGHT + inner_margin/2) + left_margin/2)
-        }).collect::<Vec<_>>()
+/// Creates a window for each node of the grid, indexed by x then y
+///
+/// Compute nodes show the code of their node in the solution, numbered by
+/// Grid::solution_nodes().
+fn create_cpu_wins(left: i32, solution: &Solution, layout: &[NodeType], width: usize, height: usize)
+    -> Vec<Vec<CpuWin>> {
+    let mut cpuwins: Vec<Vec<CpuWin>> = (0..width as i32).map(|x| {
+        (0..height as i32).map(|y| CpuWin::new(node_x(left, x), node_y(y))).collect::<Vec<_>>()
     }).collect();
 
-    for y in cpuwins.iter_mut() {
-        for cpu in y.iter_mut() {
-            cpu.refresh();
+    for (i, node) in Grid::solution_nodes(layout).into_iter().enumerate() {
+        let cpu = &mut cpuwins[i % width][i / width];
+        match node {
+            Some(node) => cpu.set_code(solution.node(node)),
+            None => cpu.set_node_type(layout[i]),
         }
+        cpu.refresh();
     }
 
     cpuwins
 }
 
-pub fn gui() {
-    initscr();
-    refresh();
+/// Gap between two neighbouring nodes showing the values waiting on the ports facing across it
+///
+/// At the edge of the grid there is only a node on one side.
+struct PortWin {
+    win:        WINDOW,
+    /// Between two columns rather than two rows
+    across:     bool,
+    /// Node to the left or above
+    before:     Option<(usize, usize)>,
+    /// Node to the right or below
+    after:      Option<(usize, usize)>,
+}
+
+impl PortWin {
+    /// Creates the gap before the node at (x, y), to its left if `across` and above it otherwise
+    fn new(left: i32, x: usize, y: usize, across: bool, width: usize, height: usize) -> PortWin {
+        let win = if across {
+            newwin(CPUWIN_HEIGHT, INNER_MARGIN, node_y(y as i32), node_x(left, x as i32) - INNER_MARGIN)
+        } else {
+            newwin(INNER_MARGIN/2, CPUWIN_WIDTH, node_y(y as i32) - INNER_MARGIN/2, node_x(left, x as i32))
+        };
+        let before = match (across, x, y) {
+            (true, 0, _) | (false, _, 0) => None,
+            (true, x, y) => Some((x - 1, y)),
+            (false, x, y) => Some((x, y - 1)),
+        };
+        let after = if x < width && y < height { Some((x, y)) } else { None };
+        PortWin { win, across, before, after }
+    }
+
+    /// Value waiting on the port of a node facing across the gap
+    fn value(ports: &GridPorts, node: Option<(usize, usize)>, port: instruction::Port) -> String {
+        node.and_then(|(x, y)| ports.ports_at(x, y).peek(port))
+            .map_or_else(String::new, |v| v.to_string())
+    }
+
+    fn update(&mut self, ports: &GridPorts) {
+        werase(self.win);
+        if self.across {
+            let mid = CPUWIN_HEIGHT / 2;
+            if self.before.is_some() {
+                let right = Self::value(ports, self.before, instruction::Port::Right);
+                mvwprintw(self.win, mid - 2, 0, &format!("{:^4}", right));
+                mvwprintw(self.win, mid - 1, 0, " -> ");
+            }
+            if self.after.is_some() {
+                let left = Self::value(ports, self.after, instruction::Port::Left);
+                mvwprintw(self.win, mid + 1, 0, " <- ");
+                mvwprintw(self.win, mid + 2, 0, &format!("{:^4}", left));
+            }
+        } else {
+            let (down_x, up_x) = (CPUWIN_WIDTH / 2 - 2, CPUWIN_WIDTH / 2 + 2);
+            /* Above the top row, an input stream's next value is on its way down */
+            let down = match self.before {
+                Some(_) => Some(Self::value(ports, self.before, instruction::Port::Down)),
+                None => self.after.and_then(|(x, _)| ports.input(x)).map(|input| {
+                    input.values().get(input.position()).map_or_else(String::new, |v| v.to_string())
+                }),
+            };
+            if let Some(down) = down {
+                mvwprintw(self.win, 0, down_x - 5, &format!("{:>4} |", down));
+                mvwprintw(self.win, 1, down_x, "v");
+            }
+            if self.after.is_some() {
+                let up = Self::value(ports, self.after, instruction::Port::Up);
+                mvwprintw(self.win, 0, up_x, "^");
+                mvwprintw(self.win, 1, up_x, &format!("| {:<4}", up));
+            }
+        }
+        wrefresh(self.win);
+    }
+}
+
+impl Drop for PortWin {
+    fn drop(&mut self) {
+        delwin(self.win);
+    }
+}
+
+/// Creates a window for every gap between nodes and at the edges of the grid
+fn create_port_wins(left: i32, width: usize, height: usize) -> Vec<PortWin> {
+    let across = (0..height).flat_map(|y| (0..=width).map(move |x| (x, y, true)));
+    let down = (0..=height).flat_map(|y| (0..width).map(move |x| (x, y, false)));
+    across.chain(down).map(|(x, y, across)| PortWin::new(left, x, y, across, width, height)).collect()
+}
+
+fn update_cpu_wins(cpuwins: &mut [Vec<CpuWin>], grid: &Grid) {
+    for (x, col) in cpuwins.iter_mut().enumerate() {
+        for (y, cpuwin) in col.iter_mut().enumerate() {
+            match *grid.node_at(x, y) {
+                Node::Compute(ref cpu) => cpuwin.update(cpu),
+                Node::Stack(ref stack) => cpuwin.update_stack(stack),
+                Node::Damaged => (),
+            }
+        }
+    }
+}
+
+/* Width of one column of values in the input and output panel */
+const IO_COL_WIDTH: i32 = 5;
+
+/// Panel left of the grid listing the values of every input and output
+///
+/// Like the game's left column, the next value of each input is
+/// highlighted, and each output's expected values are shown next to the
+/// ones received with wrong values highlighted. Image consoles show the
+/// image drawn so far with wrong pixels highlighted. If the values don't fit
+/// on the screen they scroll to keep the current ones in view.
+struct IoWin {
+    win:        WINDOW,
+    title:      String,
+    /// Name and column of each input stream
+    inputs:     Vec<(String, usize)>,
+    /// Name and column of each output stream
+    outputs:    Vec<(String, usize)>,
+    /// Name and column of each image console
+    images:     Vec<(String, usize)>,
+    /// Most values in any stream
+    rows:       i32,
+    /// Rows of values that fit in the window, which sets where images go
+    visible:    i32,
+    width:      i32,
+}
+
+impl IoWin {
+    fn new(term: WINDOW, puzzle: &Puzzle) -> IoWin {
+        let inputs: Vec<(String, usize)> = puzzle.inputs.iter()
+            .map(|i| (i.name.clone(), i.column)).collect();
+        let outputs = puzzle.outputs.iter().filter(|o| !Self::is_image(&o.expected))
+            .map(|o| (o.name.clone(), o.column)).collect();
+        let images: Vec<(String, usize)> = puzzle.outputs.iter().filter(|o| Self::is_image(&o.expected))
+            .map(|o| (o.name.clone(), o.column)).collect();
+        let rows = puzzle.inputs.iter().map(|i| i.values.len())
+            .chain(puzzle.outputs.iter().map(|o| match o.expected {
+                Expected::Values(ref values) => values.len(),
+                Expected::Image(_) => 0,
+            }))
+            .max().unwrap_or(0) as i32;
+
+        let width = Self::width(&puzzle.outputs, inputs.len());
+        /* Title, stream names and values, then each image with its name */
+        let height = 3 + rows + 1 + images.len() as i32 * (1 + IMAGE_HEIGHT as i32 + 1);
+        let mut lines: i32 = 0;
+        let mut cols: i32 = 0;
+        getmaxyx(term, &mut lines, &mut cols);
+        let visible = (rows - (height - (lines - 2)).max(0)).max(1).min(rows);
+
+        IoWin {
+            win:    newwin(height.min(lines - 2), width, 1, 1),
+            title:  puzzle.title.clone(),
+            inputs,
+            outputs,
+            images,
+            rows,
+            visible,
+            width,
+        }
+    }
+
+    /// Columns needed for a puzzle's inputs and outputs
+    fn width(outputs: &[PuzzleOutput], inputs: usize) -> i32 {
+        let streams = outputs.iter().filter(|o| !Self::is_image(&o.expected)).count();
+        let width = (inputs as i32 + 2 * streams as i32) * IO_COL_WIDTH;
+        if outputs.len() > streams {
+            width.max(IMAGE_WIDTH as i32)
+        } else {
+            width
+        }
+    }
+
+    fn is_image(expected: &Expected) -> bool {
+        match *expected {
+            Expected::Image(_) => true,
+            Expected::Values(_) => false,
+        }
+    }
+
+    /// First row of values to show, keeping the next value of every stream in
+    /// view where the window allows
+    fn first_row(&self, grid: &Grid) -> usize {
+        let inputs = self.inputs.iter().filter_map(|&(_, column)| grid.ports().input(column))
+            .map(|input| input.position());
+        let outputs = self.outputs.iter().filter_map(|&(_, column)| grid.output(column))
+            .map(|output| output.values().len());
+        let current = inputs.chain(outputs).max().unwrap_or(0) as i32;
+        (current - self.visible / 2).min(self.rows - self.visible).max(0) as usize
+    }
+
+    /// Prints a value, highlighted if `highlight`
+    fn print(&mut self, y: i32, x: i32, s: &str, highlight: bool) {
+        let attr = if highlight { A_STANDOUT() } else { 0 };
+        wattr_on(self.win, attr);
+        mvwprintw(self.win, y, x, s);
+        wattr_off(self.win, attr);
+    }
+
+    fn update(&mut self, grid: &Grid) {
+        werase(self.win);
+        wattr_on(self.win, A_BOLD());
+        mvwprintw(self.win, 0, 0, &self.title.chars().take(self.width as usize).collect::<String>());
+        wattr_off(self.win, A_BOLD());
+
+        let name = |n: &str, w: i32| format!("{:^1$.1$}", n, w as usize);
+        let first = self.first_row(grid);
+        let last = first + self.visible as usize;
+        let shown = |row: usize| row >= first && row < last;
+        let y = |row: usize| 3 + (row - first) as i32;
+        let mut x = 0;
+        for i in 0..self.inputs.len() {
+            let (ref n, column) = self.inputs[i];
+            mvwprintw(self.win, 2, x, &name(n, IO_COL_WIDTH));
+            if let Some(input) = grid.ports().input(column) {
+                for (row, v) in input.values().iter().enumerate().filter(|&(row, _)| shown(row)) {
+                    self.print(y(row), x, &format!("{:>4}", v), row == input.position());
+                }
+            }
+            x += IO_COL_WIDTH;
+        }
+
+        for i in 0..self.outputs.len() {
+            let (ref n, column) = self.outputs[i];
+            mvwprintw(self.win, 2, x, &name(n, 2 * IO_COL_WIDTH));
+            if let Some(output) = grid.output(column) {
+                for (row, v) in output.expected().iter().enumerate().filter(|&(row, _)| shown(row)) {
+                    mvwprintw(self.win, y(row), x, &format!("{:>4}", v));
+                }
+                for (row, v) in output.values().iter().enumerate().filter(|&(row, _)| shown(row)) {
+                    let wrong = output.expected().get(row) != Some(v);
+                    self.print(y(row), x + IO_COL_WIDTH, &format!("{:>4}", v), wrong);
+                }
+            }
+            x += 2 * IO_COL_WIDTH;
+        }
+
+        let mut y = 3 + self.visible + 1;
+        for i in 0..self.images.len() {
+            let (ref n, column) = self.images[i];
+            mvwprintw(self.win, y, 0, &name(n, IMAGE_WIDTH as i32));
+            if let Some(console) = grid.image(column) {
+                for py in 0..IMAGE_HEIGHT {
+                    for px in 0..IMAGE_WIDTH {
+                        let c = console.image().get(px, py);
+                        let wrong = c != console.expected().get(px, py);
+                        self.print(y + 1 + py as i32, px as i32, &c.to_char().to_string(), wrong);
+                    }
+                }
+            }
+            y += 1 + IMAGE_HEIGHT as i32 + 1;
+        }
+        wrefresh(self.win);
+    }
+}
+
+impl Drop for IoWin {
+    fn drop(&mut self) {
+        delwin(self.win);
+    }
+}
+
+/// Line across the bottom of the screen showing the cycle and run mode
+struct StatusWin {
+    win:    WINDOW,
+}
+
+impl StatusWin {
+    fn new(term: WINDOW) -> StatusWin {
+        let mut lines: i32 = 0;
+        let mut cols: i32 = 0;
+        getmaxyx(term, &mut lines, &mut cols);
+        StatusWin { win: newwin(1, cols, lines - 1, 0) }
+    }
+
+    /// Shows the cycle and mode followed by a message
+    fn update(&mut self, cycle: u64, mode: RunMode, message: &str) {
+        let status = format!(" CYCLE {:<8} MODE {:<6} {}", cycle, mode, message);
+        mvwprintw(self.win, 0, 0, &status);
+        wclrtoeol(self.win);
+        wrefresh(self.win);
+    }
+}
+
+impl Drop for StatusWin {
+    fn drop(&mut self) {
+        wclear(self.win);
+        delwin(self.win);
+    }
+}
+
+static KEY_HELP: &str = "[space] step  [r] run  [f] fast  [p] pause  [x] stop  [e] edit  [q] quit";
+static EDIT_HELP: &str = "[tab] next node  [esc] done";
+
+/// How the grid is being run, like the debugger of the game
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum RunMode {
+    /// At the initial state
+    Stop,
+    /// Paused, running one cycle per key press
+    Step,
+    /// Running slowly enough to follow
+    Run,
+    /// Running as fast as the screen keeps up with
+    Fast,
+    /// Editing code, which resets the grid when done
+    Edit,
+}
+
+/* Milliseconds between screen updates and cycles run in between */
+const RUN_DELAY: i32 = 100;
+const FAST_DELAY: i32 = 10;
+const FAST_CYCLES: u32 = 50;
+
+impl RunMode {
+    /// How long to wait for a key before running more cycles, -1 for ever
+    fn delay(self) -> i32 {
+        match self {
+            RunMode::Run => RUN_DELAY,
+            RunMode::Fast => FAST_DELAY,
+            _ => -1,
+        }
+    }
+
+    /// Cycles to run each time the delay passes without a key press
+    fn cycles(self) -> u32 {
+        match self {
+            RunMode::Run => 1,
+            RunMode::Fast => FAST_CYCLES,
+            _ => 0,
+        }
+    }
+}
+
+impl fmt::Display for RunMode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        let s = match *self {
+            RunMode::Stop => "STOP",
+            RunMode::Step => "STEP",
+            RunMode::Run => "RUN",
+            RunMode::Fast => "FAST",
+            RunMode::Edit => "EDIT",
+        };
+        f.pad(s)
+    }
+}
+
+/// Every window on the screen
+struct Screen {
+    /// The whole terminal, as returned by initscr()
+    term:       WINDOW,
+    /// Only there if the puzzle has inputs or outputs
+    iowin:      Option<IoWin>,
+    cpuwins:    Vec<Vec<CpuWin>>,
+    portwins:   Vec<PortWin>,
+    status:     StatusWin,
+    /// Position of each compute node, by solution node number
+    compute:    Vec<(usize, usize)>,
+}
 
-    let mut cpuwins = create_cpu_wins();
-    let codes = ["woo", "one\ntwo\nthree"];
-    let mut codes_iter = codes.iter();
+impl Screen {
+    fn new(term: WINDOW, puzzle: &Puzzle, solution: &Solution) -> Screen {
+        clear();
+        refresh();
+
+        let iowin = if puzzle.inputs.is_empty() && puzzle.outputs.is_empty() {
+            None
+        } else {
+            Some(IoWin::new(term, puzzle))
+        };
+        /* Leave room for the panel and the arrows at the left edge of the grid */
+        let left = iowin.as_ref().map_or(LEFT_MARGIN, |io| LEFT_MARGIN.max(1 + io.width + 1 + INNER_MARGIN));
+        let (width, height) = (puzzle.width, puzzle.height);
+        let compute = Grid::solution_nodes(&puzzle.layout).iter().enumerate()
+            .filter(|&(_, node)| node.is_some())
+            .map(|(i, _)| (i % width, i / width)).collect();
+
+        Screen {
+            term,
+            iowin,
+            cpuwins:    create_cpu_wins(left, solution, &puzzle.layout, width, height),
+            portwins:   create_port_wins(left, width, height),
+            status:     StatusWin::new(term),
+            compute,
+        }
+    }
+
+    fn update(&mut self, grid: &Grid, mode: RunMode, message: &str) {
+        update_cpu_wins(&mut self.cpuwins, grid);
+        for portwin in self.portwins.iter_mut() {
+            portwin.update(grid.ports());
+        }
+        if let Some(ref mut iowin) = self.iowin {
+            iowin.update(grid);
+        }
+        self.status.update(grid.cycle(), mode, message);
+    }
+
+    /// Window of a compute node, by solution node number
+    fn cpuwin(&mut self, node: usize) -> &mut CpuWin {
+        let (x, y) = self.compute[node];
+        &mut self.cpuwins[x][y]
+    }
+
+    fn num_nodes(&self) -> usize {
+        self.compute.len()
+    }
+}
+
+/// Maps a key to an edit of the code, upper-casing letters like the game
+fn key_edit(c: i32) -> Option<Edit> {
+    match c {
+        KEY_LEFT => Some(Edit::Left),
+        KEY_RIGHT => Some(Edit::Right),
+        KEY_UP => Some(Edit::Up),
+        KEY_DOWN => Some(Edit::Down),
+        KEY_HOME => Some(Edit::Home),
+        KEY_END => Some(Edit::End),
+        KEY_BACKSPACE | 0x7f | 0x08 => Some(Edit::Backspace),
+        KEY_DC => Some(Edit::Delete),
+        KEY_ENTER | 0x0a | 0x0d => Some(Edit::Newline),
+        0x20..=0x7e => Some(Edit::Insert((c as u8 as char).to_ascii_uppercase())),
+        _ => None,
+    }
+}
+
+const KEY_ESC: i32 = 0x1b;
+const KEY_TAB: i32 = 0x09;
+
+/// Edits the code of every node until Escape is pressed
+///
+/// Each change is parsed straight away so errors show as they are typed.
+/// When done the solution is saved to its file if it changed. Returns a
+/// message for the status line.
+fn edit_code(screen: &mut Screen, puzzle: &Puzzle, solution: &mut Solution, path: Option<&str>) -> String {
+    if screen.num_nodes() == 0 {
+        return "There are no compute nodes to edit".to_string();
+    }
+    let mut node = 0;
+    let mut editor = Editor::new(solution.node(node));
+    let mut changed = false;
+
+    for col in screen.cpuwins.iter_mut() {
+        for cpuwin in col.iter_mut() {
+            cpuwin.set_line(None);
+        }
+    }
+    curs_set(CURSOR_VISIBILITY::CURSOR_VISIBLE);
+    timeout(-1);
 
     loop {
+        let cursor = editor.cursor();
+        let message = {
+            let errors = screen.cpuwin(node).errors();
+            match errors.iter().find(|e| e.line as usize == cursor.line).or_else(|| errors.first()) {
+                Some(e) => format!("@{} {}", node, e),
+                None => format!("@{} {}:{}  {}", node, cursor.line + 1, cursor.col + 1, EDIT_HELP),
+            }
+        };
+        screen.status.update(0, RunMode::Edit, &message);
+        screen.cpuwin(node).show_cursor(cursor);
+
         let c = getch();
-        if c == b'q' as i32 {
+        if c == KEY_ESC {
             break;
+        } else if c == KEY_TAB || c == KEY_BTAB {
+            let n = screen.num_nodes();
+            node = if c == KEY_TAB { (node + 1) % n } else { (node + n - 1) % n };
+            editor = Editor::new(solution.node(node));
         } else if c == KEY_RESIZE {
-            drop(cpuwins);
-            clear();
-            refresh();
-            cpuwins = create_cpu_wins();
-        } else {
-            cpuwins[0][0].set_code(codes_iter.next().unwrap_or(&""));
+            *screen = Screen::new(screen.term, puzzle, solution);
+        } else if let Some(edit) = key_edit(c) {
+            if editor.edit(edit) {
+                let src = editor.source();
+                solution.set_node(node, &src);
+                screen.cpuwin(node).set_code(&src);
+                changed = true;
+            }
         }
     }
+    curs_set(CURSOR_VISIBILITY::CURSOR_INVISIBLE);
+
+    match path {
+        _ if !changed => String::new(),
+        None => "Changes not saved, there is no solution file".to_string(),
+        Some(path) => match fs::write(path, solution.to_string()) {
+            Ok(()) => format!("Saved {}", path),
+            Err(e) => format!("Can't save {}: {}", path, e),
+        },
+    }
+}
+
+/// Describes how a test run ended
+fn outcome(status: OutputStatus, cycle: u64) -> String {
+    match status {
+        OutputStatus::Pass => format!("Passed in {} cycles", cycle),
+        OutputStatus::Fail { index } => format!("Wrong output at index {} after {} cycles", index, cycle),
+        OutputStatus::Incomplete => String::new(),
+    }
+}
+
+/// Runs the grid under the debugger controls until the user quits
+///
+/// The grid borrows its ports, so stopping rebuilds both from the solution.
+/// So does editing, as the solution may have changed. Running pauses when
+/// a CPU reaches a breakpoint or the outputs pass or fail.
+fn debug(screen: &mut Screen, puzzle: &Puzzle, solution: &mut Solution, path: Option<&str>) {
+    let mut message = String::new();
+    loop {
+        let ports = puzzle.ports();
+        /* A solution with errors can still be edited, but not run */
+        let mut grid = puzzle.grid(&ports, solution).map_err(|e| e.to_string());
+        let mut mode = RunMode::Stop;
+
+        loop {
+            match grid {
+                Ok(ref grid) => screen.update(grid, mode, if message.is_empty() { KEY_HELP } else { &message }),
+                Err(ref e) => screen.status.update(0, mode, e),
+            }
+            message.clear();
+
+            timeout(mode.delay());
+            let c = getch();
+            if c == b'q' as i32 {
+                return;
+            } else if c == b'x' as i32 || c == KEY_F(4) {
+                break;
+            } else if c == b'e' as i32 {
+                message = edit_code(screen, puzzle, solution, path);
+                break;
+            } else if c == KEY_RESIZE {
+                *screen = Screen::new(screen.term, puzzle, solution);
+            }
+
+            let grid = match grid {
+                Ok(ref mut grid) => grid,
+                Err(_) => continue,
+            };
+            let status = grid.output_status();
+            if c == ERR {
+                for _ in 0..mode.cycles() {
+                    /* A CPU blocked at a breakpoint only stops the run once */
+                    let before = grid.breakpoints_reached();
+                    grid.step();
+                    if grid.output_status() != status {
+                        break;
+                    }
+                    if grid.breakpoints_reached().iter().any(|b| !before.contains(b)) {
+                        mode = RunMode::Step;
+                        message = format!("Breakpoint reached at cycle {}", grid.cycle());
+                        break;
+                    }
+                }
+            } else if c == b' ' as i32 || c == KEY_F(6) {
+                mode = RunMode::Step;
+                grid.step();
+            } else if c == b'r' as i32 || c == KEY_F(5) {
+                mode = RunMode::Run;
+            } else if c == b'f' as i32 || c == KEY_F(7) {
+                mode = RunMode::Fast;
+            } else if c == b'p' as i32 && (mode == RunMode::Run || mode == RunMode::Fast) {
+                mode = RunMode::Step;
+            }
+
+            if status == OutputStatus::Incomplete && grid.output_status() != OutputStatus::Incomplete {
+                mode = RunMode::Step;
+                message = outcome(grid.output_status(), grid.cycle());
+            }
+        }
+    }
+}
+
+/// Runs the interface on a solution file, or on an empty grid
+///
+/// Without a puzzle the grid is the game's 4x3 compute nodes with no inputs
+/// or outputs. Space (or F6) steps one cycle, r (F5) runs, f (F7) runs
+/// fast, p pauses, x (F4) stops and resets the grid, e edits the code and
+/// q quits.
+pub fn gui(puzzle: Option<Puzzle>, path: Option<&str>) -> Result<(), String> {
+    let mut solution = match path {
+        Some(path) => {
+            let src = fs::read_to_string(path).map_err(|e| format!("{}: {}", path, e))?;
+            Solution::from_str(&src).map_err(|e| format!("{}: {}", path, e))?
+        },
+        None => Solution::default(),
+    };
+    let puzzle = puzzle.unwrap_or_else(|| Puzzle {
+        title:          String::new(),
+        description:    String::new(),
+        width:          DEFAULT_WIDTH,
+        height:         DEFAULT_HEIGHT,
+        layout:         vec![NodeType::Compute; DEFAULT_WIDTH * DEFAULT_HEIGHT],
+        inputs:         Vec::new(),
+        outputs:        Vec::new(),
+    });
+
+    /* Escape leaves the editor, so don't wait long to tell it from other keys */
+    if env::var_os("ESCDELAY").is_none() {
+        env::set_var("ESCDELAY", "25");
+    }
+    let term = initscr();
+    noecho();
+    keypad(term, true);
+    curs_set(CURSOR_VISIBILITY::CURSOR_INVISIBLE);
+
+    let mut screen = Screen::new(term, &puzzle, &solution);
+    debug(&mut screen, &puzzle, &mut solution, path);
 
-    drop(cpuwins);
+    drop(screen);
     endwin();
+    Ok(())
 }
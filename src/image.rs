@@ -0,0 +1,268 @@
+use std::fmt;
+use std::io;
+use std::str::FromStr;
+use port::{CpuWritePortsReader, ReadPort};
+use stream::OutputStatus;
+
+/// Size of the game's image console
+pub const IMAGE_WIDTH: usize = 30;
+pub const IMAGE_HEIGHT: usize = 18;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum Color {
+    #[default]
+    Black,
+    DarkGrey,
+    LightGrey,
+    White,
+}
+
+/// Characters used to draw each colour as text, darkest first
+static COLOR_CHARS: [char; 4] = [' ', '.', '+', '#'];
+
+impl Color {
+    /// Converts a value written to the console. Unknown colours draw as black.
+    pub fn from_value(val: i32) -> Color {
+        match val {
+            1 => Color::DarkGrey,
+            2 => Color::LightGrey,
+            3 => Color::White,
+            _ => Color::Black,
+        }
+    }
+
+    pub fn rgb(self) -> [u8; 3] {
+        match self {
+            Color::Black     => [0x00, 0x00, 0x00],
+            Color::DarkGrey  => [0x46, 0x46, 0x46],
+            Color::LightGrey => [0x9c, 0x9c, 0x9c],
+            Color::White     => [0xff, 0xff, 0xff],
+        }
+    }
+
+    pub fn to_char(self) -> char {
+        COLOR_CHARS[self as usize]
+    }
+
+    /// Parses a colour from its character or its number
+    pub fn from_char(c: char) -> Option<Color> {
+        match c {
+            '0'..='3' => Some(Color::from_value(c as i32 - '0' as i32)),
+            _ => COLOR_CHARS.iter().position(|&cc| cc == c).map(|i| Color::from_value(i as i32)),
+        }
+    }
+}
+
+/// Contents of an image console
+#[derive(Clone, Debug, PartialEq)]
+pub struct Image {
+    pixels: Vec<Color>,
+}
+
+impl Default for Image {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Image {
+    /// Creates an all-black image
+    pub fn new() -> Self {
+        Image { pixels: vec![Color::Black; IMAGE_WIDTH * IMAGE_HEIGHT] }
+    }
+
+    pub fn get(&self, x: usize, y: usize) -> Color {
+        self.pixels[y * IMAGE_WIDTH + x]
+    }
+
+    /// Sets a pixel. Pixels outside the image are ignored.
+    pub fn set(&mut self, x: usize, y: usize, c: Color) {
+        if x < IMAGE_WIDTH && y < IMAGE_HEIGHT {
+            self.pixels[y * IMAGE_WIDTH + x] = c;
+        }
+    }
+
+    /// Returns the first pixel, in row-major order, that differs between images
+    pub fn first_difference(&self, other: &Image) -> Option<(usize, usize)> {
+        self.pixels.iter().zip(other.pixels.iter())
+            .position(|(a, b)| a != b)
+            .map(|i| (i % IMAGE_WIDTH, i / IMAGE_WIDTH))
+    }
+
+    /// Writes the image as a binary PPM file
+    pub fn write_ppm<W: io::Write>(&self, out: &mut W) -> io::Result<()> {
+        write!(out, "P6\n{} {}\n255\n", IMAGE_WIDTH, IMAGE_HEIGHT)?;
+        for pixel in self.pixels.iter() {
+            out.write_all(&pixel.rgb())?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for Image {
+    /// Draws the image as text, one line per row
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        for row in self.pixels.chunks(IMAGE_WIDTH) {
+            let line: String = row.iter().map(|c| c.to_char()).collect();
+            writeln!(f, "{}", line)?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for Image {
+    type Err = &'static str;
+
+    /// Parses an image drawn as text, using colour characters or numbers
+    ///
+    /// Missing pixels at the end of short lines and missing lines are black.
+    fn from_str(s: &str) -> Result<Image, Self::Err> {
+        let mut image = Image::new();
+        for (y, line) in s.lines().enumerate() {
+            for (x, c) in line.chars().enumerate() {
+                if x >= IMAGE_WIDTH || y >= IMAGE_HEIGHT {
+                    return Err("Image too large");
+                }
+                image.set(x, y, Color::from_char(c).ok_or("Bad colour")?);
+            }
+        }
+        Ok(image)
+    }
+}
+
+/// Where the console is in the x, y, colours... protocol
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum DrawState {
+    X,
+    Y { x: i32 },
+    Colors { x: i32, y: i32 },
+}
+
+/// Image console output node
+///
+/// A neighbour draws by writing an x and y position followed by the colours
+/// of consecutive pixels running to the right. A negative value ends the run
+/// and the next value is a new x position.
+pub struct ImageConsole<'a> {
+    port:       CpuWritePortsReader<'a>,
+    image:      Image,
+    expected:   Image,
+    state:      DrawState,
+}
+
+impl<'a> ImageConsole<'a> {
+    pub fn new(port: CpuWritePortsReader<'a>, expected: Image) -> Self {
+        ImageConsole {
+            port,
+            image:      Image::new(),
+            expected,
+            state:      DrawState::X,
+        }
+    }
+
+    /// Takes a value from the port if one is waiting and draws with it
+    pub fn read_cycle(&mut self) {
+        if let Some(val) = self.port.read() {
+            self.draw(val);
+        }
+    }
+
+    fn draw(&mut self, val: i32) {
+        self.state = match self.state {
+            _ if val < 0 => DrawState::X,
+            DrawState::X => DrawState::Y { x: val },
+            DrawState::Y { x } => DrawState::Colors { x, y: val },
+            DrawState::Colors { x, y } => {
+                self.image.set(x as usize, y as usize, Color::from_value(val));
+                /* Positions can be as large as the word range allows */
+                DrawState::Colors { x: x.saturating_add(1), y }
+            },
+        };
+    }
+
+    pub fn image(&self) -> &Image {
+        &self.image
+    }
+
+    pub fn expected(&self) -> &Image {
+        &self.expected
+    }
+
+    /// Returns true once the image matches the expected image
+    pub fn matches(&self) -> bool {
+        self.image == self.expected
+    }
+
+    /// Status in the same terms as an output stream
+    ///
+    /// A wrong pixel can always be redrawn, so the console never fails.
+    pub fn status(&self) -> OutputStatus {
+        if self.matches() { OutputStatus::Pass } else { OutputStatus::Incomplete }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Color, Image, ImageConsole, IMAGE_HEIGHT, IMAGE_WIDTH};
+    use instruction;
+    use port::CpuWritePorts;
+    use std::str::FromStr;
+    use stream::OutputStatus;
+
+    fn write_all(ports: &CpuWritePorts, console: &mut ImageConsole, vals: &[i32]) {
+        for &val in vals {
+            ports.write_port(instruction::Port::Down, val);
+            console.read_cycle();
+        }
+    }
+
+    #[test]
+    fn test_image_console() {
+        let ports = CpuWritePorts::new();
+        let expected = Image::from_str("\n  #.\n\n   +").unwrap();
+        let mut console = ImageConsole::new(ports.get_read_port(instruction::Port::Down), expected);
+        assert!(!console.matches());
+
+        write_all(&ports, &mut console, &[2, 1, 3, 1, -1, 3, 3]);
+        assert!(!console.matches());
+        assert_eq!(console.image().first_difference(console.expected()), Some((3, 3)));
+        write_all(&ports, &mut console, &[2, -1]);
+        assert!(console.matches());
+        assert_eq!(console.status(), OutputStatus::Pass);
+        assert_eq!(console.image().get(2, 1), Color::White);
+
+        /* Runs past the right edge are clipped */
+        write_all(&ports, &mut console, &[29, 0, 3, 3, -1]);
+        assert_eq!(console.image().get(29, 0), Color::White);
+        assert_eq!(console.image().get(0, 1), Color::Black);
+        let image = console.image().clone();
+        write_all(&ports, &mut console, &[i32::MAX, 0, 3, 3, -1]);
+        assert_eq!(console.image(), &image);
+    }
+
+    #[test]
+    fn test_image_text() {
+        let image = Image::from_str("0123\n #+.").unwrap();
+        assert_eq!(image.get(1, 0), Color::DarkGrey);
+        assert_eq!(image.get(3, 0), Color::White);
+        assert_eq!(image.get(1, 1), Color::White);
+        assert_eq!(Image::from_str(&image.to_string()).unwrap(), image);
+        assert_eq!(image.to_string().lines().count(), IMAGE_HEIGHT);
+        assert!(image.to_string().lines().all(|l| l.len() == IMAGE_WIDTH));
+
+        assert_eq!(Image::from_str("x").unwrap_err(), "Bad colour");
+        assert_eq!(Image::from_str(&"0".repeat(IMAGE_WIDTH + 1)).unwrap_err(), "Image too large");
+    }
+
+    #[test]
+    fn test_image_ppm() {
+        let mut image = Image::new();
+        image.set(1, 0, Color::White);
+        let mut ppm = Vec::new();
+        image.write_ppm(&mut ppm).unwrap();
+        let header = b"P6\n30 18\n255\n";
+        assert_eq!(&ppm[..header.len()], &header[..]);
+        assert_eq!(ppm.len(), header.len() + IMAGE_WIDTH * IMAGE_HEIGHT * 3);
+        assert_eq!(&ppm[header.len()..header.len() + 6], &[0, 0, 0, 255, 255, 255]);
+    }
+}
@@ -1,6 +1,34 @@
 use std::fmt;
 use std::str::FromStr;
 
+/// What is wrong with a line of a program, as reported in a ParseError
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ErrorKind {
+    BadOpcode,
+    NumArgs,
+    LitDst,
+    BadOperand,
+    UndefinedLabel,
+    TooManyLines,
+    LineTooLong,
+    LitRange,
+}
+
+impl fmt::Display for ErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        f.write_str(match *self {
+            ErrorKind::BadOpcode        => "Bad opcode for # of arguments",
+            ErrorKind::NumArgs          => "Wrong number of arguments",
+            ErrorKind::LitDst           => "Literal not allowed as dst operand",
+            ErrorKind::BadOperand       => "Invalid operand",
+            ErrorKind::UndefinedLabel   => "Jump to undefined label",
+            ErrorKind::TooManyLines     => "Too many lines",
+            ErrorKind::LineTooLong      => "Line too long",
+            ErrorKind::LitRange         => "Literal out of range",
+        })
+    }
+}
+
 #[derive(Debug, PartialEq)]
 pub enum Instruction {
     NOP,
@@ -14,16 +42,19 @@ pub enum Instruction {
     JRO { dst: Operand },
 }
 
-pub static BAD_OPCODE_ERR: &'static str = "Bad opcode for # of arguments";
-pub static NUM_ARGS_ERR: &'static str = "Wrong number of arguments";
-pub static LIT_DST_ERR: &'static str = "Literal not allowed as dst operand";
+/// Range of literal values accepted by the game
+pub const LIT_MIN: i32 = -999;
+pub const LIT_MAX: i32 = 999;
 
-impl FromStr for Instruction {
-    type Err = &'static str;
-
-    fn from_str(insn: &str) -> Result<Instruction, Self::Err> {
-        //Nightly: let words: Vec<&str> = insn.split_whitespace().collect();
-        let words: Vec<&str> = insn.split(' ').filter(|s| *s != "").collect();
+impl Instruction {
+    /// Parses an instruction from its opcode and operand words
+    ///
+    /// On failure, also returns the index of the offending word, or None if
+    /// the instruction as a whole is at fault.
+    pub fn from_words(words: &[&str]) -> Result<Instruction, (ErrorKind, Option<usize>)> {
+        fn operand(words: &[&str], i: usize) -> Result<Operand, (ErrorKind, Option<usize>)> {
+            Operand::from_str(words[i]).map_err(|e| (e, Some(i)))
+        }
 
         match words.len() {
             1 => match words[0] {
@@ -31,35 +62,60 @@ impl FromStr for Instruction {
                 "SWP" => Ok(Instruction::SWP),
                 "SAV" => Ok(Instruction::SAV),
                 "NEG" => Ok(Instruction::NEG),
-                _ => Err(BAD_OPCODE_ERR),
+                _ => Err((ErrorKind::BadOpcode, Some(0))),
             },
 
             2 => match words[0] {
-                "ADD" => Operand::from_str(words[1]).map(|o| Instruction::ADD { addend: o }),
-                "SUB" => Operand::from_str(words[1]).map(|o| Instruction::SUB { subtrahend: o }),
+                "ADD" => operand(words, 1).map(|o| Instruction::ADD { addend: o }),
+                "SUB" => operand(words, 1).map(|o| Instruction::SUB { subtrahend: o }),
                 "JMP" => Ok(Instruction::J { cond: Condition::Unconditional, dst: words[1].to_string() }),
                 "JEZ" => Ok(Instruction::J { cond: Condition::Ez,            dst: words[1].to_string() }),
                 "JNZ" => Ok(Instruction::J { cond: Condition::Nz,            dst: words[1].to_string() }),
                 "JGZ" => Ok(Instruction::J { cond: Condition::Gz,            dst: words[1].to_string() }),
                 "JLZ" => Ok(Instruction::J { cond: Condition::Lz,            dst: words[1].to_string() }),
-                "JRO" => Operand::from_str(words[1]).map(|o| Instruction::JRO { dst: o }),
-                _ => Err(BAD_OPCODE_ERR),
+                "JRO" => operand(words, 1).map(|o| Instruction::JRO { dst: o }),
+                _ => Err((ErrorKind::BadOpcode, Some(0))),
             },
 
             3 => match words[0] {
-                "MOV" => Operand::from_str(words[1]).and_then(|s| Operand::from_str(words[2])
+                "MOV" => operand(words, 1).and_then(|s| operand(words, 2)
                     .and_then(|d| match d {
-                        Operand::Lit(_) => Err(LIT_DST_ERR),
+                        Operand::Lit(_) => Err((ErrorKind::LitDst, Some(2))),
                         _ => Ok(Instruction::MOV { src: s, dst: d })
                         })),
-                _ => Err(BAD_OPCODE_ERR),
+                _ => Err((ErrorKind::BadOpcode, Some(0))),
             },
 
-            _ => Err(NUM_ARGS_ERR)
+            _ => Err((ErrorKind::NumArgs, None))
         }
     }
 }
 
+impl fmt::Display for Instruction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        match *self {
+            Instruction::NOP                        => f.write_str("NOP"),
+            Instruction::MOV { ref src, ref dst }   => write!(f, "MOV {} {}", src, dst),
+            Instruction::SWP                        => f.write_str("SWP"),
+            Instruction::SAV                        => f.write_str("SAV"),
+            Instruction::ADD { ref addend }         => write!(f, "ADD {}", addend),
+            Instruction::SUB { ref subtrahend }     => write!(f, "SUB {}", subtrahend),
+            Instruction::NEG                        => f.write_str("NEG"),
+            Instruction::J { ref cond, ref dst }    => write!(f, "{} {}", cond, dst),
+            Instruction::JRO { ref dst }            => write!(f, "JRO {}", dst),
+        }
+    }
+}
+
+impl FromStr for Instruction {
+    type Err = ErrorKind;
+
+    fn from_str(insn: &str) -> Result<Instruction, Self::Err> {
+        let words: Vec<&str> = insn.split_whitespace().collect();
+        Instruction::from_words(&words).map_err(|(e, _)| e)
+    }
+}
+
 #[derive(Clone, Copy, Debug, PartialEq)]
 pub enum Port {
     Up,
@@ -72,13 +128,19 @@ pub enum Port {
 
 impl fmt::Display for Port {
     fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
-        let d = self as &fmt::Debug;
-        d.fmt(f)
+        f.write_str(match *self {
+            Port::Up    => "UP",
+            Port::Down  => "DOWN",
+            Port::Left  => "LEFT",
+            Port::Right => "RIGHT",
+            Port::Any   => "ANY",
+            Port::Last  => "LAST",
+        })
     }
 }
 
 impl FromStr for Port {
-    type Err = &'static str;
+    type Err = ErrorKind;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
         match s {
@@ -88,7 +150,7 @@ impl FromStr for Port {
             "RIGHT" =>  Ok(Port::Right),
             "ANY" =>    Ok(Port::Any),
             "LAST" =>   Ok(Port::Last),
-            _ =>        Err("bad port"),
+            _ =>        Err(ErrorKind::BadOperand),
         }
     }
 }
@@ -98,20 +160,35 @@ pub enum Operand {
     Lit(i32),
     Port(Port),
     ACC,
+    /// Reads as 0, writes are discarded
+    NIL,
+}
+
+impl fmt::Display for Operand {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        match *self {
+            Operand::Lit(i)  => write!(f, "{}", i),
+            Operand::Port(p) => write!(f, "{}", p),
+            Operand::ACC     => f.write_str("ACC"),
+            Operand::NIL     => f.write_str("NIL"),
+        }
+    }
 }
 
 impl FromStr for Operand {
-    type Err = &'static str;
+    type Err = ErrorKind;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
         if s == "ACC" {
             Ok(Operand::ACC)
+        } else if s == "NIL" {
+            Ok(Operand::NIL)
         } else if let Ok(as_int) = i32::from_str(s) {
             Ok(Operand::Lit(as_int))
         } else if let Ok(as_port) = Port::from_str(s) {
             Ok(Operand::Port(as_port))
         } else {
-            Err("Invalid operand")
+            Err(ErrorKind::BadOperand)
         }
     }
 }
@@ -125,12 +202,23 @@ pub enum Condition {
     Lz,
 }
 
+impl fmt::Display for Condition {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        f.write_str(match *self {
+            Condition::Unconditional => "JMP",
+            Condition::Ez            => "JEZ",
+            Condition::Nz            => "JNZ",
+            Condition::Gz            => "JGZ",
+            Condition::Lz            => "JLZ",
+        })
+    }
+}
+
 pub type Label = String;
 
 #[cfg(test)]
 mod tests {
-    use super::{Condition, Instruction, Operand, Port};
-    use super::{BAD_OPCODE_ERR,NUM_ARGS_ERR, LIT_DST_ERR};
+    use super::{Condition, ErrorKind, Instruction, Operand, Port};
     use std::str::FromStr;
 
     #[test]
@@ -141,7 +229,7 @@ mod tests {
         assert_eq!(Port::from_str("RIGHT").unwrap(), Port::Right);
         assert_eq!(Port::from_str("ANY").unwrap(),  Port::Any);
         assert_eq!(Port::from_str("LAST").unwrap(), Port::Last);
-        assert_eq!(Port::from_str("OTHER").unwrap_err(), "bad port");
+        assert_eq!(Port::from_str("OTHER").unwrap_err(), ErrorKind::BadOperand);
     }
 
     #[test]
@@ -149,7 +237,8 @@ mod tests {
         assert_eq!(Operand::from_str("ACC").unwrap(), Operand::ACC);
         assert_eq!(Operand::from_str("32").unwrap(), Operand::Lit(32));
         assert_eq!(Operand::from_str("UP").unwrap(), Operand::Port(Port::Up));
-        assert_eq!(Operand::from_str("FOO").unwrap_err(), "Invalid operand");
+        assert_eq!(Operand::from_str("NIL").unwrap(), Operand::NIL);
+        assert_eq!(Operand::from_str("FOO").unwrap_err(), ErrorKind::BadOperand);
     }
 
     #[test]
@@ -161,7 +250,7 @@ mod tests {
         assert_eq!(i("SWP"), Instruction::SWP);
         assert_eq!(i("SAV"), Instruction::SAV);
         assert_eq!(i("NEG"), Instruction::NEG);
-        assert_eq!(Instruction::from_str("BOGUS").unwrap_err(), BAD_OPCODE_ERR);
+        assert_eq!(Instruction::from_str("BOGUS").unwrap_err(), ErrorKind::BadOpcode);
 
         assert_eq!(i("ADD 10"), Instruction::ADD { addend: Operand::Lit(10) });
         assert_eq!(i("SUB 10"), Instruction::SUB { subtrahend: Operand::Lit(10) });
@@ -171,15 +260,39 @@ mod tests {
         assert_eq!(i("JGZ LOC"), Instruction::J { cond: Condition::Gz,              dst: "LOC".to_string() });
         assert_eq!(i("JLZ LOC"), Instruction::J { cond: Condition::Lz,              dst: "LOC".to_string() });
         assert_eq!(i("JRO 1"), Instruction::JRO { dst: Operand::Lit(1) });
-        assert_eq!(Instruction::from_str("JEQ LOC").unwrap_err(), BAD_OPCODE_ERR);
+        assert_eq!(Instruction::from_str("JEQ LOC").unwrap_err(), ErrorKind::BadOpcode);
 
         assert_eq!(i("MOV UP DOWN"),    Instruction::MOV { src: Operand::Port(Port::Up), dst: Operand::Port(Port::Down) });
         assert_eq!(i("MOV  UP  DOWN"),  Instruction::MOV { src: Operand::Port(Port::Up), dst: Operand::Port(Port::Down) });
         assert_eq!(i("MOV UP ACC"),     Instruction::MOV { src: Operand::Port(Port::Up), dst: Operand::ACC });
         assert_eq!(i("MOV ACC ACC"),    Instruction::MOV { src: Operand::ACC, dst: Operand::ACC });
-        assert_eq!(Instruction::from_str("MV UP ACC").unwrap_err(), BAD_OPCODE_ERR);
-        assert_eq!(Instruction::from_str("MOV UP 10").unwrap_err(), LIT_DST_ERR);
+        assert_eq!(i("MOV UP NIL"),     Instruction::MOV { src: Operand::Port(Port::Up), dst: Operand::NIL });
+        assert_eq!(i("ADD NIL"),        Instruction::ADD { addend: Operand::NIL });
+        assert_eq!(Instruction::from_str("MV UP ACC").unwrap_err(), ErrorKind::BadOpcode);
+        assert_eq!(Instruction::from_str("MOV UP 10").unwrap_err(), ErrorKind::LitDst);
 
-        assert_eq!(Instruction::from_str("1 2 3 4").unwrap_err(), NUM_ARGS_ERR);
+        assert_eq!(Instruction::from_str("1 2 3 4").unwrap_err(), ErrorKind::NumArgs);
+    }
+
+    #[test]
+    fn instruction_display() {
+        fn d(s: &str) -> String {
+            Instruction::from_str(s).unwrap().to_string()
+        }
+        assert_eq!(d("NOP"), "NOP");
+        assert_eq!(d("MOV  UP   NIL"), "MOV UP NIL");
+        assert_eq!(d("MOV -5 ACC"), "MOV -5 ACC");
+        assert_eq!(d("SUB NIL"), "SUB NIL");
+        assert_eq!(d("JGZ LOC"), "JGZ LOC");
+        assert_eq!(d("JRO LAST"), "JRO LAST");
+    }
+
+    #[test]
+    fn instruction_from_words() {
+        assert_eq!(Instruction::from_words(&["BOGUS"]).unwrap_err(), (ErrorKind::BadOpcode, Some(0)));
+        assert_eq!(Instruction::from_words(&["ADD", "FOO"]).unwrap_err(), (ErrorKind::BadOperand, Some(1)));
+        assert_eq!(Instruction::from_words(&["MOV", "FOO", "ACC"]).unwrap_err(), (ErrorKind::BadOperand, Some(1)));
+        assert_eq!(Instruction::from_words(&["MOV", "UP", "10"]).unwrap_err(), (ErrorKind::LitDst, Some(2)));
+        assert_eq!(Instruction::from_words(&["1", "2", "3", "4"]).unwrap_err(), (ErrorKind::NumArgs, None));
     }
 }
@@ -1,5 +1,15 @@
 pub mod gui_ncurses;
 pub mod instruction;
 pub mod parse;
+pub mod puzzle;
+pub mod puzzles;
 pub mod port;
 pub mod cpu;
+pub mod edit;
+pub mod grid;
+pub mod image;
+pub mod save;
+pub mod score;
+pub mod spec;
+pub mod stack;
+pub mod stream;
@@ -1,7 +1,212 @@
 extern crate tis_100;
+
+use std::env;
+use std::fs;
+use std::path::Path;
+use std::process;
+use std::str::FromStr;
 use tis_100::gui_ncurses;
+use tis_100::puzzle::Puzzle;
+use tis_100::puzzles;
+use tis_100::puzzles::CampaignPuzzle;
+use tis_100::save::Solution;
+use tis_100::score::{failure_json, score};
+use tis_100::spec::{CheckError, PuzzleSpec, DEFAULT_MAX_CYCLES, DEFAULT_SEEDS};
+
+static USAGE: &str = "\
+Usage: tis-100 [gui [--puzzle <puzzle>] [<solution>]]
+       tis-100 run --puzzle <puzzle> [--json] [--max-cycles <n>] <solution>
+
+<puzzle> is a puzzle file or the number or title of a campaign puzzle. The
+gui shows the first test set of a campaign puzzle.
+
+run exits with 0 if every test set passes, 1 if any fails and 2 if the
+puzzle or solution can't be loaded. Every test set is run, and a failure
+lists them all. With --json it prints the score, or the seed, outcome,
+cycles and wrong output index of every test set.";
+
+/// Options of the run command
+struct RunArgs {
+    puzzle:     String,
+    solution:   String,
+    json:       bool,
+    max_cycles: u64,
+}
+
+impl RunArgs {
+    fn parse<I: Iterator<Item = String>>(mut args: I) -> Result<RunArgs, String> {
+        let mut puzzle = None;
+        let mut solution = None;
+        let mut json = false;
+        let mut max_cycles = DEFAULT_MAX_CYCLES;
+
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--puzzle" => puzzle = Some(args.next().ok_or("--puzzle needs a value")?),
+                "--json" => json = true,
+                "--max-cycles" => {
+                    let n = args.next().ok_or("--max-cycles needs a value")?;
+                    max_cycles = u64::from_str(&n).map_err(|_| format!("Bad cycle count {}", n))?;
+                },
+                _ if arg.starts_with("--") => return Err(format!("Unknown option {}", arg)),
+                _ if solution.is_none() => solution = Some(arg),
+                _ => return Err(format!("Unexpected argument {}", arg)),
+            }
+        }
+
+        Ok(RunArgs {
+            puzzle:     puzzle.ok_or("No puzzle given")?,
+            solution:   solution.ok_or("No solution given")?,
+            json,
+            max_cycles,
+        })
+    }
+}
+
+/// Options of the gui command
+struct GuiArgs {
+    puzzle:     Option<String>,
+    solution:   Option<String>,
+}
+
+impl GuiArgs {
+    fn parse<I: Iterator<Item = String>>(mut args: I) -> Result<GuiArgs, String> {
+        let mut puzzle = None;
+        let mut solution = None;
+
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--puzzle" => puzzle = Some(args.next().ok_or("--puzzle needs a value")?),
+                _ if arg.starts_with("--") => return Err(format!("Unknown option {}", arg)),
+                _ if solution.is_none() => solution = Some(arg),
+                _ => return Err(format!("Unexpected argument {}", arg)),
+            }
+        }
+
+        Ok(GuiArgs { puzzle, solution })
+    }
+}
+
+fn read(path: &str) -> Result<String, String> {
+    fs::read_to_string(path).map_err(|e| format!("{}: {}", path, e))
+}
+
+/// A puzzle given on the command line
+enum PuzzleArg {
+    /// Loaded from a file, so with a single test set
+    File(Puzzle),
+    Campaign(&'static CampaignPuzzle),
+}
+
+impl PuzzleArg {
+    /// Loads a puzzle file, or failing that looks up a campaign puzzle
+    fn load(name: &str) -> Result<PuzzleArg, String> {
+        if Path::new(name).exists() {
+            Puzzle::from_str(&read(name)?)
+                .map(PuzzleArg::File)
+                .map_err(|e| format!("{}: {}", name, e))
+        } else {
+            puzzles::find(name)
+                .map(PuzzleArg::Campaign)
+                .ok_or(format!("No puzzle file or campaign puzzle {}", name))
+        }
+    }
+
+    fn spec(&self) -> &dyn PuzzleSpec {
+        match *self {
+            PuzzleArg::File(ref puzzle) => puzzle,
+            PuzzleArg::Campaign(puzzle) => puzzle,
+        }
+    }
+
+    /// Seeds of the puzzle's test sets
+    fn seeds(&self) -> &'static [u32] {
+        match *self {
+            PuzzleArg::File(_) => &DEFAULT_SEEDS[..1],
+            PuzzleArg::Campaign(_) => &DEFAULT_SEEDS[..],
+        }
+    }
+}
+
+/// Runs a solution on every test set of a puzzle and prints the outcome
+///
+/// Returns the process exit code.
+fn run(args: &RunArgs) -> Result<i32, String> {
+    let puzzle = PuzzleArg::load(&args.puzzle)?;
+    let (spec, seeds) = (puzzle.spec(), puzzle.seeds());
+    let solution = Solution::from_str(&read(&args.solution)?)
+        .map_err(|e| format!("{}: {}", args.solution, e))?;
+
+    match score(spec, &solution, seeds, args.max_cycles) {
+        Ok(s) => {
+            if args.json {
+                println!("{}", s.to_json());
+            } else {
+                println!("PASS {}: {} cycles, {} nodes, {} instructions",
+                         spec.title(), s.cycles, s.nodes, s.instructions);
+            }
+            Ok(0)
+        },
+        Err(CheckError::Grid(e)) => Err(format!("{}: {}", args.solution, e)),
+        Err(CheckError::Failed(results)) => {
+            if args.json {
+                println!("{}", failure_json(&results));
+            } else {
+                println!("FAIL {}", spec.title());
+                for result in results.iter() {
+                    println!("  {}", result);
+                }
+            }
+            Ok(1)
+        },
+    }
+}
+
+fn run_gui(args: &GuiArgs) -> i32 {
+    let puzzle = match args.puzzle {
+        Some(ref name) => match PuzzleArg::load(name) {
+            Ok(puzzle) => Some(puzzle.spec().puzzle(DEFAULT_SEEDS[0])),
+            Err(e) => {
+                eprintln!("{}", e);
+                return 2;
+            },
+        },
+        None => None,
+    };
+    match gui_ncurses::gui(puzzle, args.solution.as_deref()) {
+        Ok(()) => 0,
+        Err(e) => {
+            eprintln!("{}", e);
+            2
+        },
+    }
+}
 
-#[allow(dead_code)]
 fn main() {
-    gui_ncurses::gui();
+    let mut args = env::args().skip(1);
+    let code = match args.next().as_deref() {
+        None => run_gui(&GuiArgs { puzzle: None, solution: None }),
+        Some("gui") => match GuiArgs::parse(args) {
+            Ok(a) => run_gui(&a),
+            Err(e) => {
+                eprintln!("{}\n\n{}", e, USAGE);
+                2
+            },
+        },
+        Some("run") => match RunArgs::parse(args) {
+            Ok(a) => run(&a).unwrap_or_else(|e| {
+                eprintln!("{}", e);
+                2
+            }),
+            Err(e) => {
+                eprintln!("{}\n\n{}", e, USAGE);
+                2
+            },
+        },
+        Some(_) => {
+            eprintln!("{}", USAGE);
+            2
+        },
+    };
+    process::exit(code);
 }
@@ -1,58 +1,252 @@
-extern crate regex;
-
-use self::regex::Regex;
+use std::borrow::Cow;
+use std::error;
+use std::fmt;
+use std::ops::Range;
 use std::str::FromStr;
-use std::ascii::AsciiExt;
 use std::collections::HashMap;
-use instruction::{Instruction, Label};
+use instruction;
+use instruction::{Instruction, Label, Operand};
+
+pub use instruction::ErrorKind;
+
+/// Maximum number of lines in a node's program
+pub const MAX_LINES: usize = 15;
+/// Maximum number of characters on a line
+pub const MAX_LINE_LEN: usize = 18;
+
+/// How closely a program is checked against the game's limits
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum Validation {
+    /// Reject programs the game wouldn't accept
+    #[default]
+    Strict,
+    /// No limits on program size or literal values
+    Sandbox,
+}
+
+/// A problem found while parsing a program, along with its location
+///
+/// Lines and columns count from 0. The column span covers the offending token.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ParseError {
+    pub kind:   ErrorKind,
+    pub line:   u32,
+    pub cols:   Range<usize>,
+    pub token:  String,
+}
+
+impl ParseError {
+    fn new(kind: ErrorKind, line: u32, src: &str, cols: Range<usize>) -> Self {
+        ParseError {
+            kind,
+            line,
+            token:  src[cols.clone()].to_string(),
+            cols,
+        }
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        write!(f, "{}:{}: {}: \"{}\"", self.line + 1, self.cols.start + 1, self.kind, self.token)
+    }
+}
+
+impl error::Error for ParseError {}
 
 #[derive(Debug, PartialEq)]
 struct Line {
     label: Option<Label>,
-    insn: Option<Instruction>
+    insn: Option<Instruction>,
+    breakpoint: bool,
 }
 
-/* Matches an optional label followed by an optional instruction. Whitespace or empty string matches as well */
-static LINE_RE: &'static str = r"\s*((?P<label>\S+):)?\s*((?P<insn>\S+.*))?";
+/// Iterator over the words of a line along with the offset of each word
+///
+/// Words are separated by whitespace or commas.
+struct Words<'a> {
+    s:      &'a str,
+    pos:    usize,
+}
 
-impl FromStr for Line {
-    type Err = &'static str;
+impl<'a> Words<'a> {
+    fn new(s: &'a str, pos: usize) -> Self {
+        Words { s, pos }
+    }
+}
 
-    fn from_str(line: &str) -> Result<Line, Self::Err> {
-        let re = Regex::new(LINE_RE).unwrap(); // TODO optimize regex compilation
+fn is_separator(c: char) -> bool {
+    c.is_whitespace() || c == ','
+}
 
-        match re.captures(&line.to_ascii_uppercase()) {
-            Some(caps) => {
-                let label = caps.name("label").map(|s| s.to_string());
+impl<'a> Iterator for Words<'a> {
+    type Item = (usize, &'a str);
 
-                /* No insn regex match is ok. Else return Err() from parse_insn() or Ok(Some(Insn)) */
-                let insn: Result<Option<Instruction>, Self::Err> = caps.name("insn")
-                    .map_or(Ok(None), |s| Instruction::from_str(s).map(|i| Some(i)));
+    fn next(&mut self) -> Option<Self::Item> {
+        let rest = &self.s[self.pos..];
+        let start = self.pos + rest.find(|c| !is_separator(c))?;
+        let end = self.s[start..].find(is_separator).map_or(self.s.len(), |e| start + e);
+        self.pos = end;
+        Some((start, &self.s[start..end]))
+    }
+}
+
+/// Upper-cases a word, only allocating if it has lower-case letters
+fn uppercase(w: &str) -> Cow<'_, str> {
+    if w.bytes().any(|b| b.is_ascii_lowercase()) {
+        Cow::Owned(w.to_ascii_uppercase())
+    } else {
+        Cow::Borrowed(w)
+    }
+}
+
+/// Removes the comment from a source line
+fn strip_comment(line: &str) -> &str {
+    line.split('#').next().unwrap_or("")
+}
 
-                insn.map(|insn| Line { insn: insn, label: label })
-            },
-            None => Err("Unparsed line"),
+/// Returns the column span of the last word of the code on a line
+fn last_word_cols(line: &str) -> Range<usize> {
+    let (start, word) = Words::new(strip_comment(line), 0).last().expect("Line has no words");
+    start..start + word.len()
+}
+
+/// Most words an instruction can have
+const MAX_WORDS: usize = 3;
+
+impl Line {
+    /// Parses a single line. Errors are reported as line 0.
+    fn parse(src: &str, validation: Validation) -> Result<Line, ParseError> {
+        /* Everything after # is a comment */
+        let code = strip_comment(src);
+        let mut pos = code.len() - code.trim_start().len();
+
+        /* ! at the start of the line marks a breakpoint */
+        let breakpoint = code[pos..].starts_with('!');
+        if breakpoint {
+            pos += 1;
         }
+
+        let mut words = Words::new(code, pos);
+        let mut first = words.next();
+
+        /* A label is the first word up to its last colon. The instruction may follow without a space. */
+        let mut label = None;
+        if let Some((start, word)) = first {
+            if let Some(colon) = word.rfind(':').filter(|&c| c > 0) {
+                label = Some(word[..colon].to_ascii_uppercase());
+                first = if colon + 1 < word.len() {
+                    Some((start + colon + 1, &word[colon + 1..]))
+                } else {
+                    words.next()
+                };
+            }
+        }
+
+        let mut insn_words = [(0, ""); MAX_WORDS];
+        let mut numwords = 0;
+        for (start, word) in first.into_iter().chain(&mut words) {
+            if numwords == MAX_WORDS {
+                let end = words.last().map_or(start + word.len(), |(s, w)| s + w.len());
+                let cols = insn_words[0].0..end;
+                return Err(ParseError::new(ErrorKind::NumArgs, 0, src, cols));
+            }
+            insn_words[numwords] = (start, word);
+            numwords += 1;
+        }
+
+        /* No instruction is ok. Else return the error from the offending word */
+        let insn = if numwords == 0 {
+            None
+        } else {
+            let upper = insn_words.map(|(_, w)| uppercase(w));
+            let strs: [&str; MAX_WORDS] = [&upper[0], &upper[1], &upper[2]];
+            let insn = Instruction::from_words(&strs[..numwords]).and_then(|insn| {
+                match literal(&insn) {
+                    Some(i) if validation == Validation::Strict &&
+                               !(instruction::LIT_MIN..=instruction::LIT_MAX).contains(&i) =>
+                        Err((ErrorKind::LitRange, Some(1))),
+                    _ => Ok(insn),
+                }
+            });
+            match insn {
+                Ok(insn) => Some(insn),
+                Err((kind, word)) => {
+                    let last = insn_words[numwords - 1];
+                    let cols = match word {
+                        Some(i) => insn_words[i].0..insn_words[i].0 + insn_words[i].1.len(),
+                        None => insn_words[0].0..last.0 + last.1.len(),
+                    };
+                    return Err(ParseError::new(kind, 0, src, cols));
+                }
+            }
+        };
+
+        Ok(Line { insn, label, breakpoint })
     }
 }
 
-fn parse_program(p: &str) -> Result<Vec<Line>, &'static str> {
+impl FromStr for Line {
+    type Err = ParseError;
+
+    fn from_str(src: &str) -> Result<Line, Self::Err> {
+        Line::parse(src, Validation::Strict)
+    }
+}
+
+/// Returns the literal operand of an instruction, if it has one
+fn literal(insn: &Instruction) -> Option<i32> {
+    match *insn {
+        Instruction::MOV { src: Operand::Lit(i), .. } |
+        Instruction::ADD { addend: Operand::Lit(i) } |
+        Instruction::SUB { subtrahend: Operand::Lit(i) } |
+        Instruction::JRO { dst: Operand::Lit(i) } => Some(i),
+        _ => None,
+    }
+}
+
+/// Parses every line, collecting all errors
+fn parse_program(p: &str, validation: Validation) -> Result<Vec<Line>, Vec<ParseError>> {
+    let mut lines = Vec::new();
+    let mut errors = Vec::new();
+
     let line_strs: Vec<&str> = p.lines().collect();
-    let mut lines = Vec::with_capacity(line_strs.len());
+    /* Trailing blank lines don't count toward the line limit */
+    let numlines = line_strs.iter().rposition(|l| !l.trim().is_empty()).map_or(0, |i| i + 1);
+
+    for (i, &line_str) in line_strs.iter().enumerate() {
+        if validation == Validation::Strict {
+            if i == MAX_LINES && numlines > MAX_LINES {
+                errors.push(ParseError::new(ErrorKind::TooManyLines, i as u32, line_str, 0..line_str.len()));
+            }
+            if let Some((col, _)) = line_str.char_indices().nth(MAX_LINE_LEN) {
+                errors.push(ParseError::new(ErrorKind::LineTooLong, i as u32, line_str, col..line_str.len()));
+            }
+        }
+
+        match Line::parse(line_str, validation) {
+            Ok(line) => lines.push(line),
+            Err(e) => errors.push(ParseError { line: i as u32, ..e }),
+        }
+    }
 
-    for line_str in line_strs {
-        lines.push(try!(Line::from_str(line_str)));
+    if errors.is_empty() {
+        Ok(lines)
+    } else {
+        Err(errors)
     }
-    Ok(lines)
 }
 
 #[derive(Debug, PartialEq)]
 pub struct InstructionLine {
     insn: Instruction,
     srcline: u32,
+    breakpoint: bool,
+    /// Instruction # a jump goes to, resolved from its label
+    target: Option<u32>,
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Default, PartialEq)]
 pub struct Executable {
     lines: Vec<InstructionLine>,
     labels: HashMap<Label, u32>,
@@ -71,29 +265,75 @@ impl Executable {
         self.line_at(line).srcline
     }
 
+    /// Instruction # a jump at this line goes to, or None if it isn't a jump
+    pub fn jump_target_at(&self, line: usize) -> Option<usize> {
+        self.line_at(line).target.map(|t| t as usize)
+    }
+
+    /// Returns true if execution should stop before this instruction
+    pub fn breakpoint_at(&self, line: usize) -> bool {
+        self.line_at(line).breakpoint
+    }
+
+    /// Instruction numbers of all breakpoints
+    pub fn breakpoints(&self) -> Vec<usize> {
+        (0..self.len()).filter(|&i| self.breakpoint_at(i)).collect()
+    }
+
     pub fn len(&self) -> usize {
         self.lines.len()
     }
 
+    pub fn is_empty(&self) -> bool {
+        self.lines.is_empty()
+    }
+
+    /// Instruction # a label refers to
     pub fn label_line(&self, label: &str) -> u32 {
         self.labels[label]
     }
+
+    /// Looks up a label, returning None if it isn't defined
+    pub fn find_label(&self, label: &str) -> Option<u32> {
+        self.labels.get(label).cloned()
+    }
+}
+
+/// Parses a program, stopping at the first error
+pub fn parse(p: &str) -> Result<Executable, ParseError> {
+    parse_with(p, Validation::Strict)
+}
+
+/// Parses a program, reporting every error in it
+///
+/// Errors are ordered by line.
+pub fn parse_all(p: &str) -> Result<Executable, Vec<ParseError>> {
+    parse_all_with(p, Validation::Strict)
+}
+
+pub fn parse_with(p: &str, validation: Validation) -> Result<Executable, ParseError> {
+    parse_all_with(p, validation).map_err(|mut errors| errors.remove(0))
 }
 
-pub fn parse(p: &str) -> Result<Executable, &'static str> {
-    let mut lines = try!(parse_program(p));
+pub fn parse_all_with(p: &str, validation: Validation) -> Result<Executable, Vec<ParseError>> {
+    let mut lines = parse_program(p, validation)?;
 
-    let validlines = lines.iter().filter(|l| l.insn != None).count();
-    let numlabels = lines.iter().filter(|l| l.label != None).count();
+    let validlines = lines.iter().filter(|l| l.insn.is_some()).count();
+    let numlabels = lines.iter().filter(|l| l.label.is_some()).count();
     let mut executable = Executable { lines: Vec::with_capacity(validlines),
                                       labels: HashMap::with_capacity(numlabels) };
 
+    /* A breakpoint on a line without an instruction applies to the next instruction */
+    let mut breakpoint = false;
+
     /* Would like a better consuming iterator */
     for i in 0..lines.len() as u32 {
         let l = lines.remove(0);
 
+        breakpoint |= l.breakpoint;
         if let Some(insn) = l.insn {
-            executable.lines.push(InstructionLine { insn: insn, srcline: i });
+            executable.lines.push(InstructionLine { insn, srcline: i, breakpoint, target: None });
+            breakpoint = false;
         }
         if let Some(label) = l.label {
             executable.labels.insert(label, i);
@@ -102,52 +342,74 @@ pub fn parse(p: &str) -> Result<Executable, &'static str> {
     assert_eq!(executable.lines.len(), validlines);
     assert_eq!(executable.labels.len(), numlabels);
 
-    /* Resolve label pointers from src line to instruction # */
-    for (_, lineno) in executable.labels.iter_mut() {
-        let mut i = 0;
-        for insnline in executable.lines.iter() {
-            if insnline.srcline >= *lineno {
-                *lineno = i;
-                break;
-            }
-            i += 1;
-        }
-        assert!(i < executable.lines.len() as u32);
+    /* Resolve label pointers from src line to instruction #. Labels after
+     * the last instruction wrap around to the first. */
+    for lineno in executable.labels.values_mut() {
+        *lineno = executable.lines.iter().position(|l| l.srcline >= *lineno).unwrap_or(0) as u32;
     }
 
-    /* Make sure all JMP labels exist */
-    for line in executable.lines.iter() {
-        if let Instruction::J { cond: _, ref dst } = line.insn {
-            if !executable.labels.contains_key(dst) {
-                return Err("Jump to undefined label");
+    /* Resolve jump targets, making sure all JMP labels exist */
+    let srclines: Vec<&str> = p.lines().collect();
+    let mut errors = Vec::new();
+    for line in executable.lines.iter_mut() {
+        if let Instruction::J { ref dst, .. } = line.insn {
+            match executable.labels.get(dst) {
+                Some(&target) => line.target = Some(target),
+                None => {
+                    let src = srclines[line.srcline as usize];
+                    errors.push(ParseError::new(ErrorKind::UndefinedLabel, line.srcline, src, last_word_cols(src)));
+                },
             }
         }
     }
 
-    Ok(executable)
+    if errors.is_empty() {
+        Ok(executable)
+    } else {
+        Err(errors)
+    }
 }
 
 #[cfg(test)]
 mod tests {
-    use super::{Line, parse};
+    use super::{ErrorKind, Line, ParseError, Validation, parse, parse_all, parse_all_with, parse_with};
     use std::str::FromStr;
 
     #[test]
     fn test_parse_line() {
-        use instruction;
-        use instruction::Instruction;
+        use instruction::{Condition, Instruction, Operand, Port};
 
-        fn l(s: &str) -> Result<Line, &'static str> {
+        fn l(s: &str) -> Result<Line, ParseError> {
             println!("{}", s);
             Line::from_str(s)
         }
-        assert_eq!(l("foo: NOP").unwrap(), Line { label: Some("FOO".to_string()), insn: Some(Instruction::NOP) });
+        assert_eq!(l("foo: NOP").unwrap(), Line { label: Some("FOO".to_string()), insn: Some(Instruction::NOP), breakpoint: false });
         /* Label with : has questionable utility */
-        assert_eq!(l("foo:: NOP").unwrap(), Line { label: Some("FOO:".to_string()), insn: Some(Instruction::NOP) });
-        assert_eq!(l(" NOP ").unwrap(), Line { label: None, insn: Some(Instruction::NOP) });
-        assert_eq!(l("").unwrap(), Line { label: None, insn: None });
-        assert_eq!(l("SUB b c").unwrap_err(), instruction::BAD_OPCODE_ERR);
-        assert_eq!(l("a b c d").unwrap_err(), instruction::NUM_ARGS_ERR);
+        assert_eq!(l("foo:: NOP").unwrap(), Line { label: Some("FOO:".to_string()), insn: Some(Instruction::NOP), breakpoint: false });
+        assert_eq!(l(" NOP ").unwrap(), Line { label: None, insn: Some(Instruction::NOP), breakpoint: false });
+        assert_eq!(l("").unwrap(), Line { label: None, insn: None, breakpoint: false });
+        assert_eq!(l("SUB b c").unwrap_err().kind, ErrorKind::BadOpcode);
+        assert_eq!(l("a b c d").unwrap_err().kind, ErrorKind::NumArgs);
+
+        /* Comments */
+        assert_eq!(l("# MOV UP DOWN").unwrap(), Line { label: None, insn: None, breakpoint: false });
+        assert_eq!(l("  #comment").unwrap(), Line { label: None, insn: None, breakpoint: false });
+        assert_eq!(l("foo: NOP # wait").unwrap(), Line { label: Some("FOO".to_string()), insn: Some(Instruction::NOP), breakpoint: false });
+        assert_eq!(l("SWP#no space").unwrap(), Line { label: None, insn: Some(Instruction::SWP), breakpoint: false });
+
+        /* Breakpoints */
+        assert_eq!(l("!NOP").unwrap(), Line { label: None, insn: Some(Instruction::NOP), breakpoint: true });
+        assert_eq!(l(" ! foo: NOP").unwrap(), Line { label: Some("FOO".to_string()), insn: Some(Instruction::NOP), breakpoint: true });
+        assert_eq!(l("!").unwrap(), Line { label: None, insn: None, breakpoint: true });
+        assert_eq!(l("# !NOP").unwrap(), Line { label: None, insn: None, breakpoint: false });
+
+        /* Case and separators */
+        assert_eq!(l("top:nop").unwrap(), Line { label: Some("TOP".to_string()), insn: Some(Instruction::NOP), breakpoint: false });
+        assert_eq!(l("\tmov up,\tacc").unwrap(), Line { label: None, insn: Some(Instruction::MOV { src: Operand::Port(Port::Up), dst: Operand::ACC }), breakpoint: false });
+        assert_eq!(l("MOV UP,DOWN").unwrap(), Line { label: None, insn: Some(Instruction::MOV { src: Operand::Port(Port::Up), dst: Operand::Port(Port::Down) }), breakpoint: false });
+        assert_eq!(l("jmp top").unwrap(), Line { label: None, insn: Some(Instruction::J { cond: Condition::Unconditional, dst: "TOP".to_string() }), breakpoint: false });
+        assert_eq!(l("a:b: NOP").unwrap(), Line { label: Some("A:B".to_string()), insn: Some(Instruction::NOP), breakpoint: false });
+        assert_eq!(l(":NOP").unwrap_err().kind, ErrorKind::BadOpcode);
     }
 
     #[test]
@@ -159,4 +421,93 @@ mod tests {
             assert_eq!(l1.insn, l2.insn);
         }
     }
+
+    #[test]
+    fn test_parse_jump_targets() {
+        let e = parse("NOP\nTOP:\n\nADD 1\nJEZ TOP\nJMP END\nSWP\nEND:").unwrap();
+        assert_eq!(e.jump_target_at(0), None);
+        assert_eq!(e.jump_target_at(1), None);
+        assert_eq!(e.jump_target_at(2), Some(1));
+        /* Labels at the end of the program wrap to the first instruction */
+        assert_eq!(e.jump_target_at(3), Some(0));
+        assert_eq!(e.label_line("TOP"), 1);
+        assert_eq!(e.find_label("END"), Some(0));
+        assert_eq!(e.find_label("MISSING"), None);
+
+        /* Labels without any instructions */
+        assert!(parse("L:").unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_parse_comments_breakpoints() {
+        let e = parse("# header\nTOP: NOP # first\n!SWP\n!\n#\nSAV\nJMP TOP").unwrap();
+        assert_eq!(e.len(), 4);
+        assert_eq!(e.srcline_at(0), 1);
+        assert_eq!(e.srcline_at(1), 2);
+        assert_eq!(e.srcline_at(2), 5);
+        assert!(!e.breakpoint_at(0));
+        assert!(e.breakpoint_at(1));
+        /* Breakpoint on an empty line stops at the next instruction */
+        assert!(e.breakpoint_at(2));
+        assert!(!e.breakpoint_at(3));
+        assert_eq!(e.breakpoints(), vec![1, 2]);
+    }
+
+    #[test]
+    fn test_parse_error_location() {
+        fn err(kind: ErrorKind, line: u32, start: usize, token: &str) -> ParseError {
+            ParseError { kind, line, cols: start..start + token.len(), token: token.to_string() }
+        }
+
+        assert_eq!(Line::from_str("Foo: bogus").unwrap_err(), err(ErrorKind::BadOpcode, 0, 5, "bogus"));
+        assert_eq!(Line::from_str("! add foo # x").unwrap_err(), err(ErrorKind::BadOperand, 0, 6, "foo"));
+        assert_eq!(Line::from_str("mov up  10").unwrap_err(), err(ErrorKind::LitDst, 0, 8, "10"));
+        assert_eq!(Line::from_str("  a b c d  ").unwrap_err(), err(ErrorKind::NumArgs, 0, 2, "a b c d"));
+
+        assert_eq!(parse("NOP\n  JMP nowhere #x").unwrap_err(), err(ErrorKind::UndefinedLabel, 1, 6, "nowhere"));
+        assert_eq!(parse("NOP\nNOPE").unwrap_err().to_string(), "2:1: Bad opcode for # of arguments: \"NOPE\"");
+    }
+
+    #[test]
+    fn test_parse_all_errors() {
+        let errors = parse_all("BOGUS\nNOP\nADD X\nL: JMP L").unwrap_err();
+        assert_eq!(errors.iter().map(|e| (e.kind, e.line)).collect::<Vec<_>>(),
+                   vec![(ErrorKind::BadOpcode, 0), (ErrorKind::BadOperand, 2)]);
+
+        let errors = parse_all("JMP A\nJMP B\nA: NOP").unwrap_err();
+        assert_eq!(errors.iter().map(|e| (e.kind, e.line)).collect::<Vec<_>>(),
+                   vec![(ErrorKind::UndefinedLabel, 1)]);
+        assert_eq!(errors[0].token, "B");
+    }
+
+    #[test]
+    fn test_parse_limits() {
+        fn kinds(p: &str) -> Vec<(ErrorKind, u32)> {
+            parse_all(p).unwrap_err().iter().map(|e| (e.kind, e.line)).collect()
+        }
+
+        let fifteen = "NOP\n".repeat(15);
+        assert_eq!(parse(&fifteen).unwrap().len(), 15);
+        assert_eq!(parse(&format!("{}\n  \n", fifteen)).unwrap().len(), 15);
+        assert_eq!(kinds(&format!("{}NOP", fifteen)), vec![(ErrorKind::TooManyLines, 15)]);
+        assert_eq!(kinds(&format!("{}\nNOP", fifteen)), vec![(ErrorKind::TooManyLines, 15)]);
+
+        assert!(parse("L: MOV LEFT  RIGHT").is_ok());
+        let e = parse("L: MOV LEFT   RIGHT").unwrap_err();
+        assert_eq!((e.kind, e.cols, e.token), (ErrorKind::LineTooLong, 18..19, "T".to_string()));
+        /* Comments count too */
+        assert_eq!(kinds("NOP # long comment here\nFOO"),
+                   vec![(ErrorKind::LineTooLong, 0), (ErrorKind::BadOpcode, 1)]);
+
+        assert!(parse("MOV 999 ACC\nADD -999").is_ok());
+        let e = parse("NOP\nMOV 1000 ACC").unwrap_err();
+        assert_eq!((e.kind, e.line, e.cols, e.token), (ErrorKind::LitRange, 1, 4..8, "1000".to_string()));
+        assert_eq!(kinds("ADD -1000\nSUB 5000\nJRO 1000"),
+                   vec![(ErrorKind::LitRange, 0), (ErrorKind::LitRange, 1), (ErrorKind::LitRange, 2)]);
+
+        /* Sandbox mode has no limits */
+        let long = format!("{}ADD 5000 # a very long line indeed", fifteen);
+        assert!(parse_with(&long, Validation::Sandbox).is_ok());
+        assert!(parse_all_with(&long, Validation::Sandbox).is_ok());
+    }
 }
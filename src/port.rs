@@ -10,6 +10,29 @@ pub trait ReadPort {
     fn read(&self) -> Option<i32>;
 }
 
+impl<T: ReadPort + ?Sized> ReadPort for &T {
+    fn read(&self) -> Option<i32> {
+        (**self).read()
+    }
+}
+
+impl<T: ReadPort + ?Sized> ReadPort for Box<T> {
+    fn read(&self) -> Option<i32> {
+        (**self).read()
+    }
+}
+
+/// Port that never has a value to read
+///
+/// Used for the edges of the grid where there is no neighbour.
+pub struct NullReadPort;
+
+impl ReadPort for NullReadPort {
+    fn read(&self) -> Option<i32> {
+        None
+    }
+}
+
 pub struct CpuWritePorts {
     up:     Cell<Option<i32>>,
     down:   Cell<Option<i32>>,
@@ -18,6 +41,12 @@ pub struct CpuWritePorts {
     last:   Cell<instruction::Port>,
 }
 
+impl Default for CpuWritePorts {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl CpuWritePorts {
     pub fn new() -> Self {
         CpuWritePorts {
@@ -51,7 +80,7 @@ impl CpuWritePorts {
     /// Returns None if the port is empty
     fn read(&self, port: instruction::Port) -> Option<i32> {
         let ret = self.get_port(port).get();
-        if let Some(_) = ret {
+        if ret.is_some() {
             /* If the read is successful, clear all pending writes from the CPU.
              * This works for the write ANY case, but also works for a write to a
              * specific port because only one pending write is allowed at once */
@@ -63,23 +92,30 @@ impl CpuWritePorts {
 
     /// Returns true if no write is pending
     pub fn write_finished(&self) -> bool {
-        match self.up.get()
+        self.up.get()
             .or(self.down.get())
             .or(self.left.get())
-            .or(self.right.get()) {
-            Some(_) => false,
-            None => true,
-        }
+            .or(self.right.get()).is_none()
+    }
+
+    /// Value waiting on a port, which stays there until a neighbour reads it
+    pub fn peek(&self, port: instruction::Port) -> Option<i32> {
+        self.get_port(port).get()
     }
 
     pub fn get_last(&self) -> instruction::Port {
         self.last.get()
     }
 
-    pub fn get_read_port(&self, p: instruction::Port) -> CpuWritePortsReader {
+    pub fn get_read_port(&self, p: instruction::Port) -> CpuWritePortsReader<'_> {
         CpuWritePortsReader::new(self, p)
     }
 
+    /// Withdraws a pending write that hasn't been read yet
+    pub fn cancel_write(&self) {
+        self.set_all(None);
+    }
+
     /// Store from the CPU into the port
     ///
     /// Returns false if the port is full
@@ -102,7 +138,7 @@ impl<'a> CpuWritePortsReader<'a> {
     /// Creates a new object implementing ReadPort for a specific port of CpuWritePorts
     fn new(ports: &'a CpuWritePorts, port: instruction::Port) -> Self {
         CpuWritePortsReader {
-            ports:  ports,
+            ports,
             active: port,
         }
     }
@@ -137,7 +173,7 @@ impl<'a> From<&'a CpuWritePorts> for CpuWritePortsReaders<'a> {
 
 #[cfg(test)]
 mod tests {
-    use super::{CpuWritePorts, Port, ReadPort};
+    use super::{CpuWritePorts, ReadPort};
     use instruction;
 
     #[test]
@@ -147,7 +183,10 @@ mod tests {
          let port = ports.get_read_port(instruction::Port::Up);
          ports.write_port(instruction::Port::Up, 1);
          assert_eq!(ports.up.get(), Some(1));
+         assert_eq!(ports.peek(instruction::Port::Up), Some(1));
+         assert_eq!(ports.peek(instruction::Port::Down), None);
          assert_eq!(port.read(), Some(1));
+         assert_eq!(ports.peek(instruction::Port::Up), None);
          assert_eq!(port.read(), None);
     }
 }
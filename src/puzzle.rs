@@ -0,0 +1,344 @@
+extern crate serde_derive;
+extern crate toml;
+
+use std::error;
+use std::fmt;
+use std::str::FromStr;
+use self::serde_derive::Deserialize;
+use grid::{Grid, GridError, GridPorts, NodeType, DEFAULT_HEIGHT, DEFAULT_WIDTH};
+use image::Image;
+use save::Solution;
+use stream::InputStream;
+
+/// Values an output must produce to solve a puzzle
+#[derive(Clone, Debug, PartialEq)]
+pub enum Expected {
+    /// Numbers written to an output stream
+    Values(Vec<i32>),
+    /// Final contents of an image console
+    Image(Image),
+}
+
+/// Input stream above the top row of a puzzle's grid
+#[derive(Clone, Debug, PartialEq)]
+pub struct PuzzleInput {
+    pub name:   String,
+    pub column: usize,
+    pub values: Vec<i32>,
+}
+
+/// Output stream or image console below the bottom row of a puzzle's grid
+#[derive(Clone, Debug, PartialEq)]
+pub struct PuzzleOutput {
+    pub name:       String,
+    pub column:     usize,
+    pub expected:   Expected,
+}
+
+/// A puzzle: the grid's layout along with its inputs and expected outputs
+///
+/// Puzzles are written in TOML:
+///
+/// ```toml
+/// title = "Signal Amplifier"
+/// description = "Read a value from IN.A\nDouble the value\nWrite the value to OUT.A"
+/// width = 4                   # Optional, defaults to the game's 4x3
+/// height = 3
+/// damaged = [[0, 1], [3, 2]]  # Optional x, y positions of non-compute nodes
+/// stacks = [[1, 1]]
+///
+/// [[input]]
+/// name = "IN.A"
+/// column = 1
+/// values = [1, 2, 3]
+///
+/// [[output]]
+/// name = "OUT.A"
+/// column = 2
+/// values = [2, 4, 6]          # Or image = "..." drawn as in image::Image
+/// ```
+#[derive(Clone, Debug, PartialEq)]
+pub struct Puzzle {
+    pub title:          String,
+    pub description:    String,
+    pub width:          usize,
+    pub height:         usize,
+    /// Type of each node in row-major order
+    pub layout:         Vec<NodeType>,
+    pub inputs:         Vec<PuzzleInput>,
+    pub outputs:        Vec<PuzzleOutput>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum PuzzleError {
+    /// The file isn't valid TOML or doesn't have the expected fields
+    Syntax(toml::de::Error),
+    /// The grid has no nodes
+    EmptyGrid,
+    NodeOutsideGrid { x: usize, y: usize },
+    /// A node was listed as more than one type
+    DuplicateNode { x: usize, y: usize },
+    ColumnOutsideGrid { column: usize },
+    /// Two inputs or two outputs share a column
+    DuplicateColumn { column: usize },
+    /// An output needs exactly one of values and image
+    Expected { column: usize },
+    Image { column: usize, error: &'static str },
+}
+
+impl fmt::Display for PuzzleError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        match *self {
+            PuzzleError::Syntax(ref e) => write!(f, "{}", e),
+            PuzzleError::EmptyGrid => f.write_str("Grid has no nodes"),
+            PuzzleError::NodeOutsideGrid { x, y } =>
+                write!(f, "Node {},{} is outside the grid", x, y),
+            PuzzleError::DuplicateNode { x, y } =>
+                write!(f, "Node {},{} is listed more than once", x, y),
+            PuzzleError::ColumnOutsideGrid { column } =>
+                write!(f, "Column {} is outside the grid", column),
+            PuzzleError::DuplicateColumn { column } =>
+                write!(f, "Column {} has more than one input or output", column),
+            PuzzleError::Expected { column } =>
+                write!(f, "Output in column {} needs either values or an image", column),
+            PuzzleError::Image { column, error } =>
+                write!(f, "Output in column {}: {}", column, error),
+        }
+    }
+}
+
+impl error::Error for PuzzleError {}
+
+fn default_width() -> usize {
+    DEFAULT_WIDTH
+}
+
+fn default_height() -> usize {
+    DEFAULT_HEIGHT
+}
+
+/// Puzzle as written in the file, before validation
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct PuzzleFile {
+    title:          String,
+    #[serde(default)]
+    description:    String,
+    #[serde(default = "default_width")]
+    width:          usize,
+    #[serde(default = "default_height")]
+    height:         usize,
+    #[serde(default)]
+    damaged:        Vec<(usize, usize)>,
+    #[serde(default)]
+    stacks:         Vec<(usize, usize)>,
+    #[serde(default, rename = "input")]
+    inputs:         Vec<InputFile>,
+    #[serde(default, rename = "output")]
+    outputs:        Vec<OutputFile>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct InputFile {
+    #[serde(default)]
+    name:   String,
+    column: usize,
+    values: Vec<i32>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct OutputFile {
+    #[serde(default)]
+    name:   String,
+    column: usize,
+    values: Option<Vec<i32>>,
+    image:  Option<String>,
+}
+
+/// Checks that every column is in the grid and used at most once
+fn check_columns<I: Iterator<Item = usize>>(columns: I, width: usize) -> Result<(), PuzzleError> {
+    let mut used = vec![false; width];
+    for column in columns {
+        if column >= width {
+            return Err(PuzzleError::ColumnOutsideGrid { column });
+        }
+        if used[column] {
+            return Err(PuzzleError::DuplicateColumn { column });
+        }
+        used[column] = true;
+    }
+    Ok(())
+}
+
+impl Puzzle {
+    /// Creates the ports of the puzzle's grid with its input streams in place
+    pub fn ports(&self) -> GridPorts {
+        let mut ports = GridPorts::new(self.width, self.height);
+        for input in self.inputs.iter() {
+            ports.set_input(input.column, InputStream::new(input.values.clone()));
+        }
+        ports
+    }
+
+    /// Creates the puzzle's grid running a solution, ready to step
+    ///
+    /// `ports` should come from ports() on the same puzzle.
+    pub fn grid<'a>(&self, ports: &'a GridPorts, solution: &Solution) -> Result<Grid<'a>, GridError> {
+        let mut grid = Grid::from_solution(ports, &self.layout, solution)?;
+        for output in self.outputs.iter() {
+            match output.expected {
+                Expected::Values(ref values) => grid.set_output(output.column, values.clone()),
+                Expected::Image(ref image) => grid.set_image(output.column, image.clone()),
+            }
+        }
+        Ok(grid)
+    }
+
+    /// Number of compute nodes, which is the most a solution can use
+    pub fn compute_nodes(&self) -> usize {
+        self.layout.iter().filter(|&&n| n == NodeType::Compute).count()
+    }
+}
+
+impl FromStr for Puzzle {
+    type Err = PuzzleError;
+
+    fn from_str(s: &str) -> Result<Puzzle, Self::Err> {
+        let file: PuzzleFile = toml::from_str(s).map_err(PuzzleError::Syntax)?;
+        if file.width == 0 || file.height == 0 {
+            return Err(PuzzleError::EmptyGrid);
+        }
+
+        let mut layout = vec![NodeType::Compute; file.width * file.height];
+        let special = file.damaged.iter().map(|&pos| (pos, NodeType::Damaged))
+            .chain(file.stacks.iter().map(|&pos| (pos, NodeType::Stack)));
+        for ((x, y), node) in special {
+            if x >= file.width || y >= file.height {
+                return Err(PuzzleError::NodeOutsideGrid { x, y });
+            }
+            let slot = &mut layout[y * file.width + x];
+            if *slot != NodeType::Compute {
+                return Err(PuzzleError::DuplicateNode { x, y });
+            }
+            *slot = node;
+        }
+
+        check_columns(file.inputs.iter().map(|i| i.column), file.width)?;
+        check_columns(file.outputs.iter().map(|o| o.column), file.width)?;
+
+        let mut outputs = Vec::with_capacity(file.outputs.len());
+        for o in file.outputs {
+            let column = o.column;
+            let expected = match (o.values, o.image) {
+                (Some(values), None) => Expected::Values(values),
+                (None, Some(image)) => Expected::Image(Image::from_str(&image)
+                    .map_err(|error| PuzzleError::Image { column, error })?),
+                _ => return Err(PuzzleError::Expected { column }),
+            };
+            outputs.push(PuzzleOutput { name: o.name, column, expected });
+        }
+
+        Ok(Puzzle {
+            title:          file.title,
+            description:    file.description,
+            width:          file.width,
+            height:         file.height,
+            layout,
+            inputs:         file.inputs.into_iter()
+                .map(|i| PuzzleInput { name: i.name, column: i.column, values: i.values })
+                .collect(),
+            outputs,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Expected, Puzzle, PuzzleError};
+    use grid::NodeType;
+    use image::Color;
+    use save::Solution;
+    use std::str::FromStr;
+    use stream::OutputStatus;
+
+    static AMPLIFIER: &str = r#"
+title = "Signal Amplifier"
+description = "Double every value"
+damaged = [[0, 1], [3, 2]]
+stacks = [[3, 0]]
+
+[[input]]
+name = "IN.A"
+column = 1
+values = [1, 2, 3]
+
+[[output]]
+name = "OUT.A"
+column = 1
+values = [2, 4, 6]
+"#;
+
+    #[test]
+    fn test_puzzle_parse() {
+        let p = Puzzle::from_str(AMPLIFIER).unwrap();
+        assert_eq!(p.title, "Signal Amplifier");
+        assert_eq!((p.width, p.height), (4, 3));
+        assert_eq!(p.layout[4], NodeType::Damaged);
+        assert_eq!(p.layout[11], NodeType::Damaged);
+        assert_eq!(p.layout[3], NodeType::Stack);
+        assert_eq!(p.compute_nodes(), 9);
+        assert_eq!(p.inputs[0].name, "IN.A");
+        assert_eq!(p.outputs[0].expected, Expected::Values(vec![2, 4, 6]));
+    }
+
+    #[test]
+    fn test_puzzle_run() {
+        let p = Puzzle::from_str(AMPLIFIER).unwrap();
+        let ports = p.ports();
+        /* Nodes 0 and 1 are on the top row, node 3 is below node 1 */
+        let s = Solution::from_str("@1\nMOV UP ACC\nADD ACC\nMOV ACC DOWN\n\n\
+                                    @3\nMOV UP DOWN\n\n\
+                                    @7\nMOV UP DOWN\n").unwrap();
+        let mut grid = p.grid(&ports, &s).unwrap();
+        for _ in 0..50 {
+            grid.step();
+        }
+        assert_eq!(grid.output_status(), OutputStatus::Pass);
+    }
+
+    #[test]
+    fn test_puzzle_image() {
+        let p = Puzzle::from_str("title = \"Draw\"\n\
+                                  [[output]]\ncolumn = 0\nimage = \"\"\"\n3\n\"\"\"\n").unwrap();
+        match p.outputs[0].expected {
+            Expected::Image(ref image) => assert_eq!(image.get(0, 0), Color::White),
+            _ => panic!("Expected an image"),
+        }
+    }
+
+    #[test]
+    fn test_puzzle_errors() {
+        fn err(s: &str) -> PuzzleError {
+            Puzzle::from_str(&format!("title = \"T\"\n{}", s)).unwrap_err()
+        }
+        match Puzzle::from_str("title = 1").unwrap_err() {
+            PuzzleError::Syntax(_) => (),
+            e => panic!("Unexpected error {:?}", e),
+        }
+        match err("bogus = 1") {
+            PuzzleError::Syntax(_) => (),
+            e => panic!("Unexpected error {:?}", e),
+        }
+        assert_eq!(err("width = 0"), PuzzleError::EmptyGrid);
+        assert_eq!(err("damaged = [[4, 0]]"), PuzzleError::NodeOutsideGrid { x: 4, y: 0 });
+        assert_eq!(err("damaged = [[1, 1]]\nstacks = [[1, 1]]"), PuzzleError::DuplicateNode { x: 1, y: 1 });
+        assert_eq!(err("[[input]]\ncolumn = 4\nvalues = []"), PuzzleError::ColumnOutsideGrid { column: 4 });
+        assert_eq!(err("[[output]]\ncolumn = 0\nvalues = []\n[[output]]\ncolumn = 0\nvalues = []"),
+                   PuzzleError::DuplicateColumn { column: 0 });
+        assert_eq!(err("[[output]]\ncolumn = 2"), PuzzleError::Expected { column: 2 });
+        assert_eq!(err("[[output]]\ncolumn = 2\nimage = \"x\""),
+                   PuzzleError::Image { column: 2, error: "Bad colour" });
+    }
+}
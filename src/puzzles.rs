@@ -0,0 +1,456 @@
+use std::cmp;
+use grid::NodeType;
+use puzzle::{Expected, PuzzleInput, PuzzleOutput};
+use spec::{PuzzleSpec, Rng};
+
+/// Number of values in each of the game's input streams
+pub const STREAM_LEN: usize = 39;
+
+/// A puzzle from the game's campaign
+pub struct CampaignPuzzle {
+    /// Segment number the game shows for the puzzle
+    pub id:         &'static str,
+    title:          &'static str,
+    description:    &'static str,
+    /// Rows of the grid from the top: C compute, D damaged, S stack
+    layout:         [&'static str; 3],
+    generate:       fn(&mut Rng) -> (Vec<PuzzleInput>, Vec<PuzzleOutput>),
+}
+
+impl PuzzleSpec for CampaignPuzzle {
+    fn title(&self) -> &str {
+        self.title
+    }
+
+    fn description(&self) -> &str {
+        self.description
+    }
+
+    fn layout(&self) -> Vec<NodeType> {
+        self.layout.iter().flat_map(|row| row.chars()).map(|c| match c {
+            'C' => NodeType::Compute,
+            'D' => NodeType::Damaged,
+            'S' => NodeType::Stack,
+            _ => panic!("Bad node type in layout"),
+        }).collect()
+    }
+
+    fn generate(&self, seed: u32) -> (Vec<PuzzleInput>, Vec<PuzzleOutput>) {
+        (self.generate)(&mut Rng::new(seed))
+    }
+}
+
+/// Campaign puzzles 00150 to 43786, in the order the game presents them
+///
+/// The image console puzzles and the later segments aren't included yet.
+pub static PUZZLES: [CampaignPuzzle; 13] = [
+    CampaignPuzzle {
+        id:             "00150",
+        title:          "SELF-TEST DIAGNOSTIC",
+        description:    "READ A VALUE FROM IN.X AND WRITE THE VALUE TO OUT.X\n\
+                         READ A VALUE FROM IN.A AND WRITE THE VALUE TO OUT.A",
+        layout:         ["CCCC", "CCDC", "CCCC"],
+        generate:       self_test_diagnostic,
+    },
+    CampaignPuzzle {
+        id:             "10981",
+        title:          "SIGNAL AMPLIFIER",
+        description:    "READ A VALUE FROM IN.A\n\
+                         DOUBLE THE VALUE\n\
+                         WRITE THE VALUE TO OUT.A",
+        layout:         ["CCCC", "CCCC", "CCCD"],
+        generate:       signal_amplifier,
+    },
+    CampaignPuzzle {
+        id:             "20176",
+        title:          "DIFFERENTIAL CONVERTER",
+        description:    "READ VALUES FROM IN.A AND IN.B\n\
+                         WRITE IN.A - IN.B TO OUT.P\n\
+                         WRITE IN.B - IN.A TO OUT.N",
+        layout:         ["CCCC", "CCCD", "CCCC"],
+        generate:       differential_converter,
+    },
+    CampaignPuzzle {
+        id:             "21340",
+        title:          "SIGNAL COMPARATOR",
+        description:    "READ A VALUE FROM IN\n\
+                         WRITE 1 TO OUT.G IF IN > 0\n\
+                         WRITE 1 TO OUT.E IF IN = 0\n\
+                         WRITE 1 TO OUT.L IF IN < 0\n\
+                         WHEN A 1 IS NOT WRITTEN TO AN OUTPUT, WRITE A 0 INSTEAD",
+        layout:         ["CCCC", "CDDD", "CCCC"],
+        generate:       signal_comparator,
+    },
+    CampaignPuzzle {
+        id:             "22280",
+        title:          "SIGNAL MULTIPLEXER",
+        description:    "READ VALUES FROM IN.A AND IN.B\n\
+                         READ A VALUE FROM IN.S\n\
+                         WRITE IN.A WHEN IN.S = -1\n\
+                         WRITE IN.B WHEN IN.S = 1\n\
+                         WRITE IN.A + IN.B WHEN IN.S = 0",
+        layout:         ["CCCC", "CCCC", "DCCC"],
+        generate:       signal_multiplexer,
+    },
+    CampaignPuzzle {
+        id:             "30647",
+        title:          "SEQUENCE GENERATOR",
+        description:    "SEQUENCES ARE ZERO-TERMINATED\n\
+                         READ VALUES FROM IN.A AND IN.B\n\
+                         WRITE THE LESSER VALUE TO OUT\n\
+                         WRITE THE GREATER VALUE TO OUT\n\
+                         WRITE 0 TO END THE SEQUENCE",
+        layout:         ["CCCC", "CCCC", "DCCC"],
+        generate:       sequence_generator,
+    },
+    CampaignPuzzle {
+        id:             "31904",
+        title:          "SEQUENCE COUNTER",
+        description:    "SEQUENCES ARE ZERO-TERMINATED\n\
+                         READ A SEQUENCE FROM IN\n\
+                         WRITE THE SUM TO OUT.S\n\
+                         WRITE THE LENGTH TO OUT.L",
+        layout:         ["CCCC", "CCCC", "CCCD"],
+        generate:       sequence_counter,
+    },
+    CampaignPuzzle {
+        id:             "32050",
+        title:          "SIGNAL EDGE DETECTOR",
+        description:    "READ A VALUE FROM IN\n\
+                         COMPARE VALUE TO PREVIOUS VALUE\n\
+                         WRITE 1 IF CHANGED BY 10 OR MORE\n\
+                         IF NOT TRUE, WRITE 0 INSTEAD\n\
+                         THE FIRST VALUE IS COMPARED TO 0",
+        layout:         ["DCCC", "CCCC", "CCCC"],
+        generate:       signal_edge_detector,
+    },
+    CampaignPuzzle {
+        id:             "33762",
+        title:          "INTERRUPT HANDLER",
+        description:    "READ FROM IN.1 THROUGH IN.4\n\
+                         WRITE THE INPUT NUMBER WHEN THE VALUE GOES FROM 0 TO 1\n\
+                         TWO INTERRUPTS WILL NEVER CHANGE IN THE SAME INPUT CYCLE\n\
+                         IF NO INTERRUPT CHANGES, WRITE 0 INSTEAD",
+        layout:         ["CCCC", "CCCC", "CCCD"],
+        generate:       interrupt_handler,
+    },
+    CampaignPuzzle {
+        id:             "40196",
+        title:          "SIGNAL PATTERN DETECTOR",
+        description:    "READ A VALUE FROM IN\n\
+                         LOOK FOR THE PATTERN 0,0,0\n\
+                         WRITE 1 WHEN THE PATTERN IS FOUND\n\
+                         IF NOT FOUND, WRITE 0 INSTEAD",
+        layout:         ["CCCC", "CCCC", "DCCC"],
+        generate:       signal_pattern_detector,
+    },
+    CampaignPuzzle {
+        id:             "41427",
+        title:          "SEQUENCE PEAK DETECTOR",
+        description:    "SEQUENCES ARE ZERO-TERMINATED\n\
+                         READ A SEQUENCE FROM IN\n\
+                         WRITE THE MINIMUM VALUE TO OUT.I\n\
+                         WRITE THE MAXIMUM VALUE TO OUT.A",
+        layout:         ["CCCC", "CCCC", "CCCD"],
+        generate:       sequence_peak_detector,
+    },
+    CampaignPuzzle {
+        id:             "42656",
+        title:          "SEQUENCE REVERSER",
+        description:    "SEQUENCES ARE ZERO-TERMINATED\n\
+                         READ A SEQUENCE FROM IN\n\
+                         REVERSE THE SEQUENCE\n\
+                         WRITE THE REVERSED SEQUENCE TO OUT",
+        layout:         ["CCCC", "SCCS", "CCCC"],
+        generate:       sequence_reverser,
+    },
+    CampaignPuzzle {
+        id:             "43786",
+        title:          "SIGNAL MULTIPLIER",
+        description:    "READ VALUES FROM IN.A AND IN.B\n\
+                         MULTIPLY THE VALUES\n\
+                         WRITE THE PRODUCT TO OUT",
+        layout:         ["CCCC", "SCCS", "CCCC"],
+        generate:       signal_multiplier,
+    },
+];
+
+/// Finds a campaign puzzle by its segment number or title, ignoring case
+pub fn find(name: &str) -> Option<&'static CampaignPuzzle> {
+    PUZZLES.iter().find(|p| p.id == name || p.title.eq_ignore_ascii_case(name))
+}
+
+fn input(name: &str, column: usize, values: Vec<i32>) -> PuzzleInput {
+    PuzzleInput { name: name.to_string(), column, values }
+}
+
+fn output(name: &str, column: usize, values: Vec<i32>) -> PuzzleOutput {
+    PuzzleOutput { name: name.to_string(), column, expected: Expected::Values(values) }
+}
+
+/// Splits STREAM_LEN values into zero-terminated sequences of up to
+/// `max_len` values between min and max
+fn sequences(rng: &mut Rng, max_len: usize, min: i32, max: i32) -> Vec<Vec<i32>> {
+    let mut seqs = Vec::new();
+    let mut remaining = STREAM_LEN;
+    while remaining > 0 {
+        let len = rng.range(0, cmp::min(max_len, remaining - 1) as i32) as usize;
+        seqs.push(rng.values(len, min, max));
+        remaining -= len + 1;
+    }
+    seqs
+}
+
+/// Joins sequences, ending each with a zero
+fn terminated<I: IntoIterator<Item = Vec<i32>>>(seqs: I) -> Vec<i32> {
+    seqs.into_iter().flat_map(|mut s| { s.push(0); s }).collect()
+}
+
+fn self_test_diagnostic(rng: &mut Rng) -> (Vec<PuzzleInput>, Vec<PuzzleOutput>) {
+    let x = rng.values(STREAM_LEN, 10, 99);
+    let a = rng.values(STREAM_LEN, 10, 99);
+    (vec![input("IN.X", 0, x.clone()), input("IN.A", 3, a.clone())],
+     vec![output("OUT.X", 0, x), output("OUT.A", 3, a)])
+}
+
+fn signal_amplifier(rng: &mut Rng) -> (Vec<PuzzleInput>, Vec<PuzzleOutput>) {
+    let a = rng.values(STREAM_LEN, 10, 99);
+    let out = a.iter().map(|v| v * 2).collect();
+    (vec![input("IN.A", 1, a)],
+     vec![output("OUT.A", 2, out)])
+}
+
+fn differential_converter(rng: &mut Rng) -> (Vec<PuzzleInput>, Vec<PuzzleOutput>) {
+    let a = rng.values(STREAM_LEN, 10, 99);
+    let b = rng.values(STREAM_LEN, 10, 99);
+    let p = a.iter().zip(b.iter()).map(|(a, b)| a - b).collect();
+    let n = a.iter().zip(b.iter()).map(|(a, b)| b - a).collect();
+    (vec![input("IN.A", 1, a), input("IN.B", 2, b)],
+     vec![output("OUT.P", 1, p), output("OUT.N", 2, n)])
+}
+
+fn signal_comparator(rng: &mut Rng) -> (Vec<PuzzleInput>, Vec<PuzzleOutput>) {
+    let vals = rng.values(STREAM_LEN, -2, 2);
+    let g = vals.iter().map(|&v| (v > 0) as i32).collect();
+    let e = vals.iter().map(|&v| (v == 0) as i32).collect();
+    let l = vals.iter().map(|&v| (v < 0) as i32).collect();
+    (vec![input("IN", 0, vals)],
+     vec![output("OUT.G", 1, g), output("OUT.E", 2, e), output("OUT.L", 3, l)])
+}
+
+fn signal_multiplexer(rng: &mut Rng) -> (Vec<PuzzleInput>, Vec<PuzzleOutput>) {
+    let a = rng.values(STREAM_LEN, -30, 30);
+    let s = rng.values(STREAM_LEN, -1, 1);
+    let b = rng.values(STREAM_LEN, -30, 30);
+    let out = (0..STREAM_LEN).map(|i| match s[i] {
+        -1 => a[i],
+        1 => b[i],
+        _ => a[i] + b[i],
+    }).collect();
+    (vec![input("IN.A", 1, a), input("IN.S", 2, s), input("IN.B", 3, b)],
+     vec![output("OUT", 2, out)])
+}
+
+fn sequence_generator(rng: &mut Rng) -> (Vec<PuzzleInput>, Vec<PuzzleOutput>) {
+    let a = rng.values(STREAM_LEN / 3, 10, 99);
+    let b = rng.values(STREAM_LEN / 3, 10, 99);
+    let out = terminated(a.iter().zip(b.iter()).map(|(&a, &b)| vec![cmp::min(a, b), cmp::max(a, b)]));
+    (vec![input("IN.A", 1, a), input("IN.B", 2, b)],
+     vec![output("OUT", 2, out)])
+}
+
+fn sequence_counter(rng: &mut Rng) -> (Vec<PuzzleInput>, Vec<PuzzleOutput>) {
+    let seqs = sequences(rng, 5, 10, 99);
+    let sums = seqs.iter().map(|s| s.iter().sum()).collect();
+    let lens = seqs.iter().map(|s| s.len() as i32).collect();
+    (vec![input("IN", 1, terminated(seqs))],
+     vec![output("OUT.S", 1, sums), output("OUT.L", 2, lens)])
+}
+
+fn signal_edge_detector(rng: &mut Rng) -> (Vec<PuzzleInput>, Vec<PuzzleOutput>) {
+    /* Mostly small changes so both results are common */
+    let mut vals = Vec::with_capacity(STREAM_LEN);
+    let mut prev = 0;
+    for _ in 0..STREAM_LEN {
+        let val = if rng.range(0, 2) == 0 { rng.range(-40, 40) } else { prev + rng.range(-5, 5) };
+        vals.push(val);
+        prev = val;
+    }
+    let out = vals.iter().scan(0, |prev, &v| {
+        let edge = ((v - *prev).abs() >= 10) as i32;
+        *prev = v;
+        Some(edge)
+    }).collect();
+    (vec![input("IN", 1, vals)],
+     vec![output("OUT", 2, out)])
+}
+
+fn interrupt_handler(rng: &mut Rng) -> (Vec<PuzzleInput>, Vec<PuzzleOutput>) {
+    let mut ins: Vec<Vec<i32>> = (0..4).map(|_| Vec::with_capacity(STREAM_LEN)).collect();
+    let mut state = [0; 4];
+    let mut out = Vec::with_capacity(STREAM_LEN);
+    for _ in 0..STREAM_LEN {
+        /* At most one input changes each cycle */
+        let change = rng.range(0, 6) as usize;
+        let mut rising = 0;
+        if change < 4 {
+            state[change] ^= 1;
+            if state[change] == 1 {
+                rising = change as i32 + 1;
+            }
+        }
+        for (vals, &s) in ins.iter_mut().zip(state.iter()) {
+            vals.push(s);
+        }
+        out.push(rising);
+    }
+    let inputs = ins.into_iter().enumerate()
+        .map(|(i, vals)| input(&format!("IN.{}", i + 1), i, vals))
+        .collect();
+    (inputs, vec![output("OUT", 2, out)])
+}
+
+fn signal_pattern_detector(rng: &mut Rng) -> (Vec<PuzzleInput>, Vec<PuzzleOutput>) {
+    let vals: Vec<i32> = (0..STREAM_LEN)
+        .map(|_| if rng.range(0, 1) == 0 { 0 } else { rng.range(1, 30) })
+        .collect();
+    let out = (0..STREAM_LEN)
+        .map(|i| (i >= 2 && vals[i - 2..=i].iter().all(|&v| v == 0)) as i32)
+        .collect();
+    (vec![input("IN", 1, vals)],
+     vec![output("OUT", 2, out)])
+}
+
+fn sequence_peak_detector(rng: &mut Rng) -> (Vec<PuzzleInput>, Vec<PuzzleOutput>) {
+    /* Every sequence has at least one value so it has a minimum and maximum */
+    let mut seqs = sequences(rng, 5, 10, 99);
+    for s in seqs.iter_mut().filter(|s| s.is_empty()) {
+        s.push(rng.range(10, 99));
+    }
+    while terminated(seqs.clone()).len() > STREAM_LEN {
+        seqs.pop();
+    }
+    let mins = seqs.iter().map(|s| *s.iter().min().unwrap()).collect();
+    let maxes = seqs.iter().map(|s| *s.iter().max().unwrap()).collect();
+    (vec![input("IN", 1, terminated(seqs))],
+     vec![output("OUT.I", 1, mins), output("OUT.A", 2, maxes)])
+}
+
+fn sequence_reverser(rng: &mut Rng) -> (Vec<PuzzleInput>, Vec<PuzzleOutput>) {
+    let seqs = sequences(rng, 6, 10, 99);
+    let reversed = terminated(seqs.iter().map(|s| s.iter().rev().cloned().collect()));
+    (vec![input("IN", 1, terminated(seqs))],
+     vec![output("OUT", 2, reversed)])
+}
+
+fn signal_multiplier(rng: &mut Rng) -> (Vec<PuzzleInput>, Vec<PuzzleOutput>) {
+    let a = rng.values(STREAM_LEN, 0, 9);
+    let b = rng.values(STREAM_LEN, 0, 9);
+    let out = a.iter().zip(b.iter()).map(|(a, b)| a * b).collect();
+    (vec![input("IN.A", 1, a), input("IN.B", 2, b)],
+     vec![output("OUT", 2, out)])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{find, PUZZLES, STREAM_LEN};
+    use puzzle::Expected;
+    use save::Solution;
+    use spec::{check, PuzzleSpec, DEFAULT_MAX_CYCLES, DEFAULT_SEEDS};
+    use std::str::FromStr;
+
+    #[test]
+    fn test_puzzles_generate() {
+        for p in PUZZLES.iter() {
+            let puzzle = p.puzzle(1);
+            assert_eq!(puzzle.layout.len(), 12, "{}", p.id);
+            assert_eq!(p.puzzle(1), puzzle, "{}", p.id);
+            assert_ne!(p.puzzle(2), puzzle, "{}", p.id);
+            for i in puzzle.inputs.iter() {
+                assert!(i.values.len() <= STREAM_LEN, "{}", p.id);
+                assert!(i.column < puzzle.width, "{}", p.id);
+            }
+            for o in puzzle.outputs.iter() {
+                match o.expected {
+                    Expected::Values(ref v) => assert!(!v.is_empty(), "{}", p.id),
+                    _ => panic!("{} has an image output", p.id),
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_puzzles_find() {
+        assert_eq!(find("00150").unwrap().title(), "SELF-TEST DIAGNOSTIC");
+        assert_eq!(find("Signal Amplifier").unwrap().id, "10981");
+        assert!(find("bogus").is_none());
+    }
+
+    fn solve(id: &str, src: &str) {
+        let solution = Solution::from_str(src).unwrap();
+        let results = check(find(id).unwrap(), &solution, &DEFAULT_SEEDS, DEFAULT_MAX_CYCLES).unwrap();
+        assert_eq!(results.len(), DEFAULT_SEEDS.len());
+    }
+
+    #[test]
+    fn test_puzzles_solve() {
+        solve("00150", "@0\nMOV UP DOWN\n@3\nMOV UP DOWN\n@4\nMOV UP DOWN\n\
+                        @6\nMOV UP DOWN\n@7\nMOV UP DOWN\n@10\nMOV UP DOWN\n");
+        solve("10981", "@1\nMOV UP ACC\nADD ACC\nMOV ACC RIGHT\n@2\nMOV LEFT DOWN\n\
+                        @6\nMOV UP DOWN\n@10\nMOV UP DOWN\n");
+        solve("20176", "@1\nMOV UP ACC\nSUB RIGHT\nMOV ACC DOWN\n@2\nMOV UP LEFT\n\
+                        @5\nMOV UP ACC\nMOV ACC DOWN\nNEG\nMOV ACC RIGHT\n@6\nMOV LEFT DOWN\n\
+                        @8\nMOV UP DOWN\n@9\nMOV UP DOWN\n");
+        solve("21340", "@0\nMOV UP DOWN\n@4\nMOV UP DOWN\n@5\nMOV UP RIGHT\n\
+                        @6\nS: MOV LEFT ACC\nMOV ACC RIGHT\nJGZ P\nMOV 0 DOWN\nJMP S\n\
+                        P: MOV 1 DOWN\n@7\nS: MOV LEFT ACC\nMOV ACC RIGHT\nJEZ Z\nMOV 0 DOWN\n\
+                        JMP S\nZ: MOV 1 DOWN\n@8\nS: MOV LEFT ACC\nJLZ N\nMOV 0 DOWN\nJMP S\n\
+                        N: MOV 1 DOWN\n");
+        solve("22280", "@1\nMOV UP DOWN\n@2\nMOV UP DOWN\n@3\nMOV UP DOWN\n\
+                        @5\nMOV UP RIGHT\n@6\nMOV UP ACC\nJGZ B\nJLZ A\nMOV LEFT ACC\n\
+                        ADD RIGHT\nJMP O\nB: MOV LEFT NIL\nMOV RIGHT ACC\nJMP O\n\
+                        A: MOV RIGHT NIL\nMOV LEFT ACC\nO: MOV ACC DOWN\n\
+                        @7\nMOV UP LEFT\n@9\nMOV UP DOWN\n");
+        solve("30647", "@1\nMOV UP ACC\nMOV ACC RIGHT\nMOV ACC RIGHT\n\
+                        @2\nMOV UP ACC\nSAV\nSUB LEFT\nJGZ G\nSWP\nMOV ACC DOWN\nMOV LEFT DOWN\nJMP E\n\
+                        G: MOV LEFT DOWN\nSWP\nMOV ACC DOWN\nE: MOV 0 DOWN\n\
+                        @6\nMOV UP DOWN\n@9\nMOV UP DOWN\n");
+        solve("31904", "@1\nMOV UP ACC\nMOV ACC DOWN\nMOV ACC DOWN\nMOV ACC RIGHT\n\
+                        @2\nMOV LEFT DOWN\n@5\nL: MOV UP ACC\nJEZ E\nSWP\nADD UP\nSAV\nJMP L\n\
+                        E: SWP\nMOV ACC DOWN\nMOV UP NIL\n@6\nS: MOV UP ACC\nJEZ E\nSWP\nADD 1\n\
+                        SWP\nJMP S\nE: SWP\nMOV ACC DOWN\n@9\nMOV UP DOWN\n\
+                        @10\nMOV UP DOWN\n");
+        solve("32050", "@0\nMOV UP ACC\nSWP\nMOV ACC DOWN\nSWP\nSAV\nMOV ACC DOWN\n\
+                        @4\nS: MOV UP ACC\nNEG\nADD UP\nJGZ P\nNEG\nP: SUB 9\nJGZ O\n\
+                        MOV 0 RIGHT\nJMP S\nO: MOV 1 RIGHT\n@5\nMOV LEFT DOWN\n\
+                        @9\nMOV UP DOWN\n");
+        solve("33762", "@0\nS: MOV UP ACC\nSWP\nJEZ Z\nMOV 0 DOWN\nJMP S\nZ: SWP\nSAV\n\
+                        MOV ACC DOWN\n@1\nS: MOV UP ACC\nSWP\nJEZ Z\nMOV 0 DOWN\nJMP S\nZ: SWP\n\
+                        SAV\nJEZ O\nMOV 2 ACC\nO: MOV ACC DOWN\n\
+                        @2\nS: MOV UP ACC\nSWP\nJEZ Z\nMOV 0 DOWN\nJMP S\nZ: SWP\nSAV\nJEZ O\n\
+                        MOV 3 ACC\nO: MOV ACC DOWN\n@3\nS: MOV UP ACC\nSWP\nJEZ Z\nMOV 0 DOWN\n\
+                        JMP S\nZ: SWP\nSAV\nJEZ O\nMOV 4 ACC\nO: MOV ACC DOWN\n\
+                        @4\nMOV UP RIGHT\n@5\nMOV UP ACC\nADD LEFT\nMOV ACC RIGHT\n\
+                        @6\nMOV UP ACC\nADD LEFT\nADD RIGHT\nMOV ACC DOWN\n\
+                        @7\nMOV UP LEFT\n@10\nMOV UP DOWN\n");
+        solve("40196", "@1\nS: MOV UP ACC\nJEZ Z\nMOV 0 ACC\nSAV\nMOV 0 DOWN\nJMP S\nZ: SWP\n\
+                        ADD 1\nSAV\nSUB 2\nJGZ O\nMOV 0 DOWN\nJMP S\nO: MOV 1 DOWN\n\
+                        @5\nMOV UP RIGHT\n@6\nMOV LEFT DOWN\n\
+                        @9\nMOV UP DOWN\n");
+        solve("41427", "@1\nS: MOV UP ACC\nMOV ACC DOWN\nMOV ACC RIGHT\nJEZ S\nMOV ACC DOWN\n\
+                        MOV ACC DOWN\nMOV ACC RIGHT\nMOV ACC RIGHT\n\
+                        @2\nMOV LEFT DOWN\n@5\nL: MOV UP ACC\nJEZ E\nSWP\nADD 100\nSUB UP\n\
+                        JGZ N\nADD UP\nJMP S\nN: MOV UP ACC\nS: SUB 100\nSAV\nJMP L\nE: SWP\n\
+                        ADD 100\nMOV ACC DOWN\n@6\nL: MOV UP ACC\nJEZ E\nSWP\nSUB UP\nJLZ N\n\
+                        ADD UP\nSAV\nJMP L\nN: MOV UP NIL\nJMP L\nE: SWP\nMOV ACC DOWN\n\
+                        @9\nMOV UP DOWN\n@10\nMOV UP DOWN\n");
+        solve("42656", "@1\nMOV UP DOWN\n@4\nS: MOV UP ACC\nJEZ E\nMOV ACC LEFT\nSWP\nADD 1\n\
+                        SWP\nJMP S\nE: SWP\nJEZ D\nP: MOV LEFT RIGHT\nSUB 1\nJGZ P\n\
+                        D: MOV 0 RIGHT\n@5\nMOV LEFT DOWN\n@8\nMOV UP DOWN\n");
+        solve("43786", "@1\nMOV UP DOWN\n@2\nMOV UP DOWN\n@4\nS: MOV UP ACC\nSAV\n\
+                        L: MOV RIGHT ACC\nJEZ S\nSWP\nMOV ACC RIGHT\nSAV\nJMP L\n\
+                        @5\nS: MOV UP ACC\nL: JEZ E\nSUB 1\nMOV 1 LEFT\nSWP\nADD LEFT\nSWP\n\
+                        JMP L\nE: MOV 0 LEFT\nSWP\nMOV ACC DOWN\n\
+                        @8\nMOV UP DOWN\n");
+    }
+}
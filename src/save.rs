@@ -0,0 +1,145 @@
+use std::fmt;
+use std::str::FromStr;
+use parse::{parse, Executable, ParseError};
+
+/// Source code for every node of a solution
+///
+/// This is the game's save file format. Each node's program follows an `@N`
+/// header line, where N is the node number.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Solution {
+    nodes: Vec<String>,
+}
+
+impl Solution {
+    pub fn new(nodes: Vec<String>) -> Self {
+        Solution { nodes }
+    }
+
+    /// Number of nodes in the solution, including empty ones
+    pub fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+
+    /// Source of a node, which is empty if the solution doesn't have it
+    pub fn node(&self, node: usize) -> &str {
+        self.nodes.get(node).map_or("", |s| s.as_str())
+    }
+
+    /// Replaces the source of a node, adding empty nodes up to it if needed
+    pub fn set_node(&mut self, node: usize, src: &str) {
+        while self.nodes.len() <= node {
+            self.nodes.push(String::new());
+        }
+        self.nodes[node] = src.to_string();
+    }
+
+    /// Parses every node's program
+    ///
+    /// On failure, returns the number of the node containing the error.
+    pub fn parse(&self) -> Result<Vec<Executable>, (usize, ParseError)> {
+        self.nodes.iter().enumerate().map(|(i, n)| parse(n).map_err(|e| (i, e))).collect()
+    }
+}
+
+/// Returns the node number if this line is an @N header
+fn node_header(line: &str) -> Option<Result<usize, &'static str>> {
+    line.trim().strip_prefix('@')
+        .map(|num| usize::from_str(num).map_err(|_| "Bad node number"))
+}
+
+impl FromStr for Solution {
+    type Err = &'static str;
+
+    fn from_str(s: &str) -> Result<Solution, Self::Err> {
+        let mut nodes: Vec<Vec<&str>> = Vec::new();
+
+        for line in s.lines() {
+            match node_header(line) {
+                Some(num) => {
+                    let num = num?;
+                    if num < nodes.len() {
+                        return Err("Node numbers out of order");
+                    }
+                    while nodes.len() <= num {
+                        nodes.push(Vec::new());
+                    }
+                },
+                None => match nodes.last_mut() {
+                    Some(node) => node.push(line),
+                    None => if !line.trim().is_empty() {
+                        return Err("Code before first node");
+                    },
+                },
+            }
+        }
+
+        /* The game separates nodes with blank lines, which aren't part of the code */
+        let nodes = nodes.into_iter().map(|mut lines| {
+            while lines.last().is_some_and(|l| l.trim().is_empty()) {
+                lines.pop();
+            }
+            lines.join("\n")
+        }).collect();
+
+        Ok(Solution { nodes })
+    }
+}
+
+impl fmt::Display for Solution {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        for (i, node) in self.nodes.iter().enumerate() {
+            write!(f, "@{}\n{}\n\n", i, node)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Solution;
+    use std::str::FromStr;
+
+    #[test]
+    fn test_solution_from_str() {
+        let s = Solution::from_str("@0\nMOV UP DOWN\n\n@1\n\n\n@2\nL: ADD 1\nJMP L\n\n").unwrap();
+        assert_eq!(s.len(), 3);
+        assert_eq!(s.node(0), "MOV UP DOWN");
+        assert_eq!(s.node(1), "");
+        assert_eq!(s.node(2), "L: ADD 1\nJMP L");
+
+        let e = s.parse().unwrap();
+        assert_eq!(e.len(), 3);
+        assert!(e[1].is_empty());
+        assert_eq!(e[2].len(), 2);
+
+        /* Missing nodes are kept as empty nodes */
+        let s = Solution::from_str("@1\nNOP").unwrap();
+        assert_eq!(s.len(), 2);
+        assert_eq!(s.node(0), "");
+        assert_eq!(s.node(5), "");
+
+        assert_eq!(Solution::from_str("@1\n@0\n").unwrap_err(), "Node numbers out of order");
+        assert_eq!(Solution::from_str("@x\n").unwrap_err(), "Bad node number");
+        assert_eq!(Solution::from_str("NOP\n@0\n").unwrap_err(), "Code before first node");
+        let (node, err) = Solution::from_str("@0\n\n@1\nBOGUS\n").unwrap().parse().unwrap_err();
+        assert_eq!(node, 1);
+        assert_eq!(err.kind, ::parse::ErrorKind::BadOpcode);
+    }
+
+    #[test]
+    fn test_solution_round_trip() {
+        let text = "@0\nMOV UP DOWN\n\n@1\n\n\n@2\nNOP\nNOP\n\n";
+        let s = Solution::from_str(text).unwrap();
+        assert_eq!(s.to_string(), text);
+        assert_eq!(Solution::from_str(&s.to_string()).unwrap(), s);
+
+        let mut s = Solution::default();
+        s.set_node(1, "NOP");
+        assert_eq!(s.to_string(), "@0\n\n\n@1\nNOP\n\n");
+    }
+}
@@ -0,0 +1,121 @@
+extern crate serde_derive;
+extern crate serde_json;
+
+use self::serde_derive::Serialize;
+use grid::GridError;
+use save::Solution;
+use spec::{check, CheckError, PuzzleSpec, TestResult};
+use stream::OutputStatus;
+
+/// The numbers the game shows in its histograms for a solution
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+pub struct Score {
+    /// Cycles to complete each test set, averaged and rounded up
+    pub cycles:         u64,
+    /// Nodes with at least one instruction
+    pub nodes:          usize,
+    /// Instructions across every node
+    pub instructions:   usize,
+}
+
+impl Score {
+    /// Formats the score as a JSON object with one field per number
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(self).expect("Score is always representable as JSON")
+    }
+}
+
+/// One test set of a failed solution as written in JSON
+#[derive(Serialize)]
+struct TestSet {
+    seed:       u32,
+    passed:     bool,
+    cycles:     u64,
+    /// Index of the first wrong output, or null if no output was wrong
+    index:      Option<usize>,
+    deadlocked: bool,
+}
+
+#[derive(Serialize)]
+struct Failure {
+    test_sets:  Vec<TestSet>,
+}
+
+/// Formats the results of a failed solution as a JSON object holding every
+/// test set
+pub fn failure_json(results: &[TestResult]) -> String {
+    let test_sets = results.iter().map(|r| TestSet {
+        seed:       r.seed,
+        passed:     r.passed(),
+        cycles:     r.cycles,
+        index:      match r.status {
+            OutputStatus::Fail { index } => Some(index),
+            _ => None,
+        },
+        deadlocked: r.deadlocked,
+    }).collect();
+    serde_json::to_string(&Failure { test_sets }).expect("Failure is always representable as JSON")
+}
+
+/// Runs a solution on the test set generated from each seed and scores it
+///
+/// Fails like spec::check() if any test set fails.
+pub fn score<S: PuzzleSpec + ?Sized>(spec: &S, solution: &Solution, seeds: &[u32], max_cycles: u64)
+    -> Result<Score, CheckError> {
+    let executables = solution.parse()
+        .map_err(|(node, error)| CheckError::Grid(GridError::Parse { node, error }))?;
+    let results = check(spec, solution, seeds, max_cycles)?;
+
+    let total: u64 = results.iter().map(|r| r.cycles).sum();
+    let runs = results.len().max(1) as u64;
+    Ok(Score {
+        cycles:         total.div_ceil(runs),
+        nodes:          executables.iter().filter(|e| !e.is_empty()).count(),
+        instructions:   executables.iter().map(|e| e.len()).sum(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{failure_json, score, Score};
+    use puzzle::Puzzle;
+    use save::Solution;
+    use spec::{run, CheckError};
+    use std::str::FromStr;
+
+    static PUZZLE: &str = "title = \"Double\"\nwidth = 2\nheight = 1\n\
+                           [[input]]\ncolumn = 0\nvalues = [1, 2, 3]\n\
+                           [[output]]\ncolumn = 1\nvalues = [2, 4, 6]\n";
+
+    #[test]
+    fn test_score() {
+        let p = Puzzle::from_str(PUZZLE).unwrap();
+        let s = Solution::from_str("@0\nMOV UP ACC\nADD ACC\nL: MOV ACC RIGHT\n\n@1\nMOV LEFT DOWN\n").unwrap();
+        let (_, cycles) = run(&p, &s, 1000).unwrap();
+        let result = score(&p, &s, &[1, 2], 1000).unwrap();
+        assert_eq!(result, Score { cycles, nodes: 2, instructions: 4 });
+        assert_eq!(result.to_json(), format!("{{\"cycles\":{},\"nodes\":2,\"instructions\":4}}", cycles));
+
+        /* Empty nodes don't count */
+        let s = Solution::from_str("@0\n\n@1\nMOV 2 DOWN\nMOV 4 DOWN\nMOV 6 DOWN\nL: JMP L\n").unwrap();
+        assert_eq!(score(&p, &s, &[1], 1000).unwrap().nodes, 1);
+
+        match score(&p, &Solution::from_str("@1\nMOV 1 DOWN").unwrap(), &[1], 1000) {
+            Err(CheckError::Failed(r)) =>
+                assert_eq!(failure_json(&r), format!("{{\"test_sets\":[{{\"seed\":1,\"passed\":false,\
+                                                      \"cycles\":{},\"index\":0,\"deadlocked\":false}}]}}", r[0].cycles)),
+            r => panic!("Unexpected result {:?}", r),
+        }
+        match score(&p, &Solution::from_str("@0\nL: JMP L").unwrap(), &[1, 2], 100) {
+            Err(CheckError::Failed(r)) =>
+                assert_eq!(failure_json(&r), "{\"test_sets\":[\
+                                              {\"seed\":1,\"passed\":false,\"cycles\":100,\"index\":null,\"deadlocked\":false},\
+                                              {\"seed\":2,\"passed\":false,\"cycles\":100,\"index\":null,\"deadlocked\":false}]}"),
+            r => panic!("Unexpected result {:?}", r),
+        }
+        match score(&p, &Solution::from_str("@2\nMOV 1 DOWN").unwrap(), &[1], 1000) {
+            Err(CheckError::Grid(_)) => (),
+            r => panic!("Unexpected result {:?}", r),
+        }
+    }
+}
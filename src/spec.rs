@@ -0,0 +1,316 @@
+use std::error;
+use std::fmt;
+use grid::{GridError, NodeType, DEFAULT_HEIGHT, DEFAULT_WIDTH};
+use puzzle::{Puzzle, PuzzleInput, PuzzleOutput};
+use save::Solution;
+use stream::OutputStatus;
+
+/// Seeds of the test sets a solution is checked against by default
+pub const DEFAULT_SEEDS: [u32; 3] = [1, 2, 3];
+
+/// Cycles after which a run that hasn't produced all its output fails
+pub const DEFAULT_MAX_CYCLES: u64 = 100_000;
+
+/// Small deterministic random number generator for puzzle inputs
+///
+/// This is xorshift64*. The sequence for a seed must never change, or test
+/// sets and the scores measured on them would change with it.
+#[derive(Clone, Debug)]
+pub struct Rng {
+    state: u64,
+}
+
+impl Rng {
+    pub fn new(seed: u32) -> Self {
+        /* The state must never be zero */
+        Rng { state: u64::from(seed) ^ 0x9e37_79b9_7f4a_7c15 }
+    }
+
+    pub fn next_u32(&mut self) -> u32 {
+        self.state ^= self.state >> 12;
+        self.state ^= self.state << 25;
+        self.state ^= self.state >> 27;
+        (self.state.wrapping_mul(0x2545_f491_4f6c_dd1d) >> 32) as u32
+    }
+
+    /// Returns a value between min and max inclusive
+    pub fn range(&mut self, min: i32, max: i32) -> i32 {
+        assert!(min <= max);
+        let span = (i64::from(max) - i64::from(min) + 1) as u64;
+        (i64::from(min) + (u64::from(self.next_u32()) % span) as i64) as i32
+    }
+
+    /// Returns `len` values between min and max inclusive
+    pub fn values(&mut self, len: usize, min: i32, max: i32) -> Vec<i32> {
+        (0..len).map(|_| self.range(min, max)).collect()
+    }
+}
+
+/// A puzzle whose inputs and expected outputs are generated from a seed
+pub trait PuzzleSpec {
+    fn title(&self) -> &str;
+
+    fn description(&self) -> &str {
+        ""
+    }
+
+    fn width(&self) -> usize {
+        DEFAULT_WIDTH
+    }
+
+    fn height(&self) -> usize {
+        DEFAULT_HEIGHT
+    }
+
+    /// Type of each node in row-major order
+    fn layout(&self) -> Vec<NodeType> {
+        vec![NodeType::Compute; self.width() * self.height()]
+    }
+
+    /// Creates the inputs and expected outputs of one test set
+    fn generate(&self, seed: u32) -> (Vec<PuzzleInput>, Vec<PuzzleOutput>);
+
+    /// Creates the puzzle for one test set
+    fn puzzle(&self, seed: u32) -> Puzzle {
+        let (inputs, outputs) = self.generate(seed);
+        Puzzle {
+            title:          self.title().to_string(),
+            description:    self.description().to_string(),
+            width:          self.width(),
+            height:         self.height(),
+            layout:         self.layout(),
+            inputs,
+            outputs,
+        }
+    }
+}
+
+/// A puzzle file has a single test set whatever the seed
+impl PuzzleSpec for Puzzle {
+    fn title(&self) -> &str {
+        &self.title
+    }
+
+    fn description(&self) -> &str {
+        &self.description
+    }
+
+    fn width(&self) -> usize {
+        self.width
+    }
+
+    fn height(&self) -> usize {
+        self.height
+    }
+
+    fn layout(&self) -> Vec<NodeType> {
+        self.layout.clone()
+    }
+
+    fn generate(&self, _seed: u32) -> (Vec<PuzzleInput>, Vec<PuzzleOutput>) {
+        (self.inputs.clone(), self.outputs.clone())
+    }
+
+    fn puzzle(&self, _seed: u32) -> Puzzle {
+        self.clone()
+    }
+}
+
+/// Outcome of running a solution on one test set
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct TestResult {
+    pub seed:       u32,
+    /// Incomplete if the run timed out or deadlocked
+    pub status:     OutputStatus,
+    pub cycles:     u64,
+    /// The run stopped early because no node could make progress
+    pub deadlocked: bool,
+}
+
+impl TestResult {
+    pub fn passed(&self) -> bool {
+        self.status == OutputStatus::Pass
+    }
+}
+
+impl fmt::Display for TestResult {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        let TestResult { seed, status, cycles, deadlocked } = *self;
+        match status {
+            OutputStatus::Incomplete if deadlocked =>
+                write!(f, "Seed {}: deadlocked after {} cycles", seed, cycles),
+            OutputStatus::Pass =>
+                write!(f, "Seed {}: passed in {} cycles", seed, cycles),
+            OutputStatus::Fail { index } =>
+                write!(f, "Seed {}: wrong output at index {} after {} cycles", seed, index, cycles),
+            OutputStatus::Incomplete =>
+                write!(f, "Seed {}: output incomplete after {} cycles", seed, cycles),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum CheckError {
+    /// The solution doesn't fit the puzzle
+    Grid(GridError),
+    /// The solution failed at least one test set. Holds the result of every
+    /// test set, in seed order.
+    Failed(Vec<TestResult>),
+}
+
+impl fmt::Display for CheckError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        match *self {
+            CheckError::Grid(ref e) => write!(f, "{}", e),
+            CheckError::Failed(ref results) => {
+                let failed: Vec<String> = results.iter()
+                    .filter(|r| !r.passed())
+                    .map(|r| r.to_string())
+                    .collect();
+                f.write_str(&failed.join("; "))
+            },
+        }
+    }
+}
+
+impl error::Error for CheckError {}
+
+/// Runs a solution until its outputs pass, fail or `max_cycles` runs out
+///
+/// A run that deadlocks stops early, so an incomplete run that ends before
+/// `max_cycles` has deadlocked.
+pub fn run(puzzle: &Puzzle, solution: &Solution, max_cycles: u64) -> Result<(OutputStatus, u64), GridError> {
+    let ports = puzzle.ports();
+    let mut grid = puzzle.grid(&ports, solution)?;
+    while grid.output_status() == OutputStatus::Incomplete && grid.cycle() < max_cycles && !grid.deadlocked() {
+        grid.step();
+    }
+    Ok((grid.output_status(), grid.cycle()))
+}
+
+/// Runs a solution on the test set generated from each seed
+///
+/// Every test set is run even after one fails, so a failure reports them all.
+pub fn check<S: PuzzleSpec + ?Sized>(spec: &S, solution: &Solution, seeds: &[u32], max_cycles: u64)
+    -> Result<Vec<TestResult>, CheckError> {
+    let mut results = Vec::with_capacity(seeds.len());
+    for &seed in seeds {
+        let (status, cycles) = run(&spec.puzzle(seed), solution, max_cycles).map_err(CheckError::Grid)?;
+        let deadlocked = status == OutputStatus::Incomplete && cycles < max_cycles;
+        results.push(TestResult { seed, status, cycles, deadlocked });
+    }
+    if results.iter().all(TestResult::passed) {
+        Ok(results)
+    } else {
+        Err(CheckError::Failed(results))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{check, CheckError, PuzzleSpec, Rng, TestResult};
+    use grid::NodeType;
+    use puzzle::{Expected, PuzzleInput, PuzzleOutput};
+    use save::Solution;
+    use std::str::FromStr;
+    use stream::OutputStatus;
+
+    /// Passes values straight through from the top left to the bottom left
+    struct Passthrough;
+
+    impl PuzzleSpec for Passthrough {
+        fn title(&self) -> &str {
+            "Passthrough"
+        }
+
+        fn width(&self) -> usize {
+            1
+        }
+
+        fn height(&self) -> usize {
+            1
+        }
+
+        fn generate(&self, seed: u32) -> (Vec<PuzzleInput>, Vec<PuzzleOutput>) {
+            let values = Rng::new(seed).values(5, 0, 2);
+            (vec![PuzzleInput { name: "IN".to_string(), column: 0, values: values.clone() }],
+             vec![PuzzleOutput { name: "OUT".to_string(), column: 0, expected: Expected::Values(values) }])
+        }
+    }
+
+    #[test]
+    fn test_rng() {
+        let a: Vec<u32> = (0..4).scan(Rng::new(7), |r, _| Some(r.next_u32())).collect();
+        let b: Vec<u32> = (0..4).scan(Rng::new(7), |r, _| Some(r.next_u32())).collect();
+        assert_eq!(a, b);
+        assert_ne!(Rng::new(7).next_u32(), Rng::new(8).next_u32());
+
+        let mut rng = Rng::new(0);
+        let values = rng.values(1000, -3, 3);
+        assert!(values.iter().all(|v| (-3..=3).contains(v)));
+        assert!(values.contains(&-3) && values.contains(&3));
+        assert!(rng.values(100, -999, 999).iter().all(|v| (-999..=999).contains(v)));
+    }
+
+    #[test]
+    fn test_spec_puzzle() {
+        let p = Passthrough.puzzle(5);
+        assert_eq!(p.layout, vec![NodeType::Compute]);
+        assert_eq!(p.inputs[0].values, Rng::new(5).values(5, 0, 2));
+        assert_eq!(Passthrough.puzzle(5), p);
+    }
+
+    #[test]
+    fn test_check() {
+        let good = Solution::from_str("@0\nMOV UP DOWN").unwrap();
+        let results = check(&Passthrough, &good, &[1, 2, 3], 100).unwrap();
+        assert_eq!(results.len(), 3);
+        assert!(results.iter().all(|r| r.passed() && r.cycles > 0));
+
+        /* Fails whenever a 0 comes through, and every test set is reported */
+        let bad = Solution::from_str("@0\nMOV UP ACC\nJEZ S\nMOV ACC DOWN\nS:").unwrap();
+        let has_zero = |s: u32| Passthrough.puzzle(s).inputs[0].values.contains(&0);
+        let pass = (1..).find(|&s| !has_zero(s)).unwrap();
+        let fail = (1..).find(|&s| has_zero(s)).unwrap();
+        match check(&Passthrough, &bad, &[fail, pass], 100) {
+            Err(CheckError::Failed(r)) => {
+                assert_eq!(r.iter().map(|r| (r.seed, r.passed())).collect::<Vec<_>>(),
+                           vec![(fail, false), (pass, true)]);
+                assert_eq!(CheckError::Failed(r.clone()).to_string(), r[0].to_string());
+            },
+            r => panic!("Unexpected result {:?}", r),
+        }
+
+        let wrong = Solution::from_str("@0\nMOV 5 DOWN").unwrap();
+        match check(&Passthrough, &wrong, &[1, 2], 100) {
+            Err(CheckError::Failed(r)) => {
+                assert!(r.iter().all(|r| r.status == OutputStatus::Fail { index: 0 }));
+                assert_eq!(CheckError::Failed(r.clone()).to_string(), format!("{}; {}", r[0], r[1]));
+            },
+            r => panic!("Unexpected result {:?}", r),
+        }
+
+        /* Runs out of cycles without finishing */
+        let slow = Solution::from_str("@0\nL: JMP L").unwrap();
+        match check(&Passthrough, &slow, &[1], 100) {
+            Err(CheckError::Failed(r)) =>
+                assert_eq!(r, vec![TestResult { seed: 1, status: OutputStatus::Incomplete, cycles: 100,
+                                                deadlocked: false }]),
+            r => panic!("Unexpected result {:?}", r),
+        }
+
+        /* Stops as soon as nothing can move */
+        let stuck = Solution::from_str("@0\nMOV LEFT DOWN").unwrap();
+        match check(&Passthrough, &stuck, &[1], 100) {
+            Err(CheckError::Failed(r)) => {
+                assert!(r[0].deadlocked && r[0].cycles < 5);
+                assert_eq!(r[0].to_string(), format!("Seed 1: deadlocked after {} cycles", r[0].cycles));
+            },
+            r => panic!("Unexpected result {:?}", r),
+        }
+        match check(&Passthrough, &Solution::from_str("@1\nNOP").unwrap(), &[1], 100) {
+            Err(CheckError::Grid(_)) => (),
+            r => panic!("Unexpected result {:?}", r),
+        }
+    }
+}
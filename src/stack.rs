@@ -0,0 +1,175 @@
+use cpu::CpuReadPorts;
+use instruction;
+use port::CpuWritePorts;
+
+/// Number of values a stack node can hold
+pub const STACK_SIZE: usize = 15;
+
+/// T30 stack memory node
+///
+/// Values written by any neighbour are pushed. The top of the stack is
+/// offered to every neighbour through the node's write ports and popped when
+/// one of them reads it. Writes to a full stack block like writes to a CPU
+/// that isn't reading.
+pub struct Stack<'a> {
+    values:     Vec<i32>,
+    outports:   &'a CpuWritePorts,
+    inports:    CpuReadPorts<'a>,
+    /// Index of the value currently offered on the write ports
+    offered:    Option<usize>,
+}
+
+impl<'a> Stack<'a> {
+    pub fn new(write_ports: &'a CpuWritePorts, read_ports: CpuReadPorts<'a>) -> Stack<'a> {
+        Stack {
+            values:     Vec::with_capacity(STACK_SIZE),
+            outports:   write_ports,
+            inports:    read_ports,
+            offered:    None,
+        }
+    }
+
+    /// Accepts writes from neighbours while there is room
+    pub fn execute(&mut self) {
+        for port in [instruction::Port::Up,
+                     instruction::Port::Down,
+                     instruction::Port::Left,
+                     instruction::Port::Right].iter() {
+            if self.values.len() >= STACK_SIZE {
+                break;
+            }
+            if let Some(val) = self.inports.get_port(*port).read() {
+                self.values.push(val);
+            }
+        }
+    }
+
+    /// Pops the value read by a neighbour and offers the new top of the stack
+    ///
+    /// Like Cpu::write_cycle(), this must be called after execute().
+    pub fn write_cycle(&mut self) {
+        if let Some(i) = self.offered {
+            if self.outports.write_finished() {
+                self.values.remove(i);
+                self.offered = None;
+            }
+        }
+
+        /* A push since the last offer changes the top of the stack */
+        let top = self.values.len().checked_sub(1);
+        if self.offered != top {
+            self.outports.cancel_write();
+            if let Some(i) = top {
+                self.outports.write_port(instruction::Port::Any, self.values[i]);
+            }
+            self.offered = top;
+        }
+    }
+
+    /// Contents of the stack, from bottom to top
+    pub fn values(&self) -> &[i32] {
+        &self.values
+    }
+
+    pub fn len(&self) -> usize {
+        self.values.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Stack, STACK_SIZE};
+    use cpu::CpuReadPorts;
+    use instruction;
+    use port::{CpuWritePorts, CpuWritePortsReaders, NullReadPort, ReadPort};
+
+    fn step(stack: &mut Stack) {
+        stack.execute();
+        stack.write_cycle();
+    }
+
+    #[test]
+    fn test_stack_push_pop() {
+        let ports = CpuWritePorts::new();
+        let inports = CpuWritePorts::new();
+        let readers = CpuWritePortsReaders::from(&inports);
+        let mut stack = Stack::new(&ports, CpuReadPorts::new(readers.up, readers.down, NullReadPort, NullReadPort));
+        let up = ports.get_read_port(instruction::Port::Up);
+        let down = ports.get_read_port(instruction::Port::Down);
+
+        step(&mut stack);
+        assert!(stack.is_empty());
+        assert_eq!(up.read(), None);
+
+        inports.write_port(instruction::Port::Up, 1);
+        step(&mut stack);
+        inports.write_port(instruction::Port::Down, 2);
+        step(&mut stack);
+        assert_eq!(stack.values(), &[1, 2]);
+        assert!(inports.write_finished());
+
+        /* Reads from any side pop the most recent value */
+        assert_eq!(down.read(), Some(2));
+        assert_eq!(up.read(), None);
+        step(&mut stack);
+        assert_eq!(stack.values(), &[1]);
+        assert_eq!(up.read(), Some(1));
+        step(&mut stack);
+        assert!(stack.is_empty());
+        assert_eq!(up.read(), None);
+    }
+
+    #[test]
+    fn test_stack_push_replaces_offer() {
+        let ports = CpuWritePorts::new();
+        let inports = CpuWritePorts::new();
+        let readers = CpuWritePortsReaders::from(&inports);
+        let mut stack = Stack::new(&ports, CpuReadPorts::new(readers.up, NullReadPort, NullReadPort, NullReadPort));
+        let left = ports.get_read_port(instruction::Port::Left);
+
+        inports.write_port(instruction::Port::Up, 1);
+        step(&mut stack);
+        inports.write_port(instruction::Port::Up, 2);
+        step(&mut stack);
+        assert_eq!(left.read(), Some(2));
+
+        /* Value read and a new one pushed in the same cycle */
+        inports.write_port(instruction::Port::Up, 3);
+        step(&mut stack);
+        assert_eq!(stack.values(), &[1, 3]);
+        assert_eq!(left.read(), Some(3));
+        step(&mut stack);
+        assert_eq!(left.read(), Some(1));
+    }
+
+    #[test]
+    fn test_stack_full() {
+        let ports = CpuWritePorts::new();
+        let inports = CpuWritePorts::new();
+        let readers = CpuWritePortsReaders::from(&inports);
+        let mut stack = Stack::new(&ports, CpuReadPorts::new(NullReadPort, NullReadPort, NullReadPort, readers.right));
+
+        for i in 0..STACK_SIZE as i32 {
+            inports.write_port(instruction::Port::Right, i);
+            step(&mut stack);
+        }
+        assert_eq!(stack.len(), STACK_SIZE);
+
+        /* Writes block until there is room */
+        inports.write_port(instruction::Port::Right, 100);
+        step(&mut stack);
+        step(&mut stack);
+        assert!(!inports.write_finished());
+        assert_eq!(stack.len(), STACK_SIZE);
+
+        assert_eq!(ports.get_read_port(instruction::Port::Right).read(), Some(14));
+        step(&mut stack);
+        step(&mut stack);
+        assert!(inports.write_finished());
+        assert_eq!(stack.values()[STACK_SIZE - 1], 100);
+    }
+}
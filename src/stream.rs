@@ -0,0 +1,216 @@
+use std::cell::Cell;
+use port::{CpuWritePortsReader, ReadPort};
+
+/// Input node feeding a fixed list of values to a neighbour
+///
+/// Each read returns the next value. Once every value has been read, reads
+/// return None forever and the neighbour blocks.
+pub struct InputStream {
+    values: Vec<i32>,
+    pos:    Cell<usize>,
+}
+
+impl InputStream {
+    pub fn new(values: Vec<i32>) -> Self {
+        InputStream {
+            values,
+            pos:    Cell::new(0),
+        }
+    }
+
+    /// Every value in the stream, including ones already read
+    pub fn values(&self) -> &[i32] {
+        &self.values
+    }
+
+    /// Index of the next value to be read
+    pub fn position(&self) -> usize {
+        self.pos.get()
+    }
+
+    /// Returns true once every value has been read
+    pub fn is_exhausted(&self) -> bool {
+        self.pos.get() >= self.values.len()
+    }
+
+    /// Starts the stream over from the first value
+    pub fn reset(&self) {
+        self.pos.set(0);
+    }
+}
+
+impl ReadPort for InputStream {
+    fn read(&self) -> Option<i32> {
+        let val = self.values.get(self.pos.get()).cloned();
+        if val.is_some() {
+            self.pos.set(self.pos.get() + 1);
+        }
+        val
+    }
+}
+
+/// Result of comparing an output stream against its expected values
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum OutputStatus {
+    /// Every expected value was received and nothing else
+    Pass,
+    /// The value at this index was wrong or wasn't expected at all
+    Fail { index: usize },
+    /// Values received so far are correct, but more are expected
+    Incomplete,
+}
+
+impl OutputStatus {
+    /// Combines the status of several outputs
+    ///
+    /// Any failure fails the whole set, and all must pass for the set to pass.
+    pub fn combine(self, other: OutputStatus) -> OutputStatus {
+        match (self, other) {
+            (OutputStatus::Fail { .. }, _) => self,
+            (_, OutputStatus::Fail { .. }) => other,
+            (OutputStatus::Pass, OutputStatus::Pass) => OutputStatus::Pass,
+            _ => OutputStatus::Incomplete,
+        }
+    }
+}
+
+/// Output node receiving values from a neighbour
+///
+/// Every value is recorded with the cycle it arrived in and checked against
+/// the expected sequence.
+pub struct OutputStream<'a> {
+    port:       CpuWritePortsReader<'a>,
+    expected:   Vec<i32>,
+    received:   Vec<(u64, i32)>,
+}
+
+impl<'a> OutputStream<'a> {
+    pub fn new(port: CpuWritePortsReader<'a>, expected: Vec<i32>) -> Self {
+        OutputStream {
+            port,
+            expected,
+            received:   Vec::new(),
+        }
+    }
+
+    /// Takes a value from the port if one is waiting
+    ///
+    /// `cycle` is recorded along with the value.
+    pub fn read_cycle(&mut self, cycle: u64) {
+        if let Some(val) = self.port.read() {
+            self.received.push((cycle, val));
+        }
+    }
+
+    pub fn expected(&self) -> &[i32] {
+        &self.expected
+    }
+
+    /// Values received so far along with the cycle each arrived in
+    pub fn received(&self) -> &[(u64, i32)] {
+        &self.received
+    }
+
+    /// Values received so far
+    pub fn values(&self) -> Vec<i32> {
+        self.received.iter().map(|&(_, v)| v).collect()
+    }
+
+    pub fn status(&self) -> OutputStatus {
+        let mismatch = self.received.iter().zip(self.expected.iter())
+            .position(|(&(_, got), &want)| got != want);
+
+        if let Some(index) = mismatch {
+            OutputStatus::Fail { index }
+        } else if self.received.len() > self.expected.len() {
+            OutputStatus::Fail { index: self.expected.len() }
+        } else if self.received.len() == self.expected.len() {
+            OutputStatus::Pass
+        } else {
+            OutputStatus::Incomplete
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{InputStream, OutputStatus, OutputStream};
+    use cpu::{Cpu, CpuReadPorts};
+    use instruction;
+    use parse;
+    use port::{CpuWritePorts, NullReadPort, ReadPort};
+
+    #[test]
+    fn test_input_stream() {
+        let input = InputStream::new(vec![1, 2]);
+        assert!(!input.is_exhausted());
+        assert_eq!(input.read(), Some(1));
+        assert_eq!(input.position(), 1);
+        assert_eq!(input.read(), Some(2));
+        assert!(input.is_exhausted());
+        assert_eq!(input.read(), None);
+        assert_eq!(input.position(), 2);
+
+        input.reset();
+        assert_eq!(input.read(), Some(1));
+
+        assert!(InputStream::new(vec![]).is_exhausted());
+    }
+
+    #[test]
+    fn test_input_stream_cpu() {
+        let input = InputStream::new(vec![5, 7]);
+        let e = parse::parse("ADD UP").unwrap();
+        let ports = CpuWritePorts::new();
+        let mut cpu = Cpu::new(e, &ports, CpuReadPorts::new(&input, NullReadPort, NullReadPort, NullReadPort));
+        for _ in 0..4 {
+            cpu.execute();
+            cpu.write_cycle();
+        }
+        assert_eq!(cpu.acc(), 12);
+        assert!(input.is_exhausted());
+    }
+
+    #[test]
+    fn test_output_stream() {
+        let ports = CpuWritePorts::new();
+        let mut output = OutputStream::new(ports.get_read_port(instruction::Port::Down), vec![1, 2]);
+        output.read_cycle(1);
+        assert_eq!(output.status(), OutputStatus::Incomplete);
+
+        ports.write_port(instruction::Port::Down, 1);
+        output.read_cycle(2);
+        assert!(ports.write_finished());
+        assert_eq!(output.status(), OutputStatus::Incomplete);
+        ports.write_port(instruction::Port::Down, 2);
+        output.read_cycle(5);
+        assert_eq!(output.status(), OutputStatus::Pass);
+        assert_eq!(output.received(), &[(2, 1), (5, 2)]);
+        assert_eq!(output.values(), vec![1, 2]);
+
+        /* Anything more than expected fails */
+        ports.write_port(instruction::Port::Down, 3);
+        output.read_cycle(6);
+        assert_eq!(output.status(), OutputStatus::Fail { index: 2 });
+    }
+
+    #[test]
+    fn test_output_stream_mismatch() {
+        let ports = CpuWritePorts::new();
+        let mut output = OutputStream::new(ports.get_read_port(instruction::Port::Down), vec![1, 2, 3]);
+        for (cycle, val) in [1, 5].iter().enumerate() {
+            ports.write_port(instruction::Port::Down, *val);
+            output.read_cycle(cycle as u64);
+        }
+        assert_eq!(output.status(), OutputStatus::Fail { index: 1 });
+    }
+
+    #[test]
+    fn test_output_status_combine() {
+        let fail = OutputStatus::Fail { index: 3 };
+        assert_eq!(OutputStatus::Pass.combine(OutputStatus::Pass), OutputStatus::Pass);
+        assert_eq!(OutputStatus::Pass.combine(OutputStatus::Incomplete), OutputStatus::Incomplete);
+        assert_eq!(OutputStatus::Incomplete.combine(fail), fail);
+        assert_eq!(fail.combine(OutputStatus::Pass), fail);
+    }
+}
@@ -0,0 +1,71 @@
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+use std::process::{Command, Output};
+
+static GOOD: &str = "@1\nMOV UP ACC\nADD ACC\nMOV ACC RIGHT\n@2\nMOV LEFT DOWN\n\
+                     @6\nMOV UP DOWN\n@10\nMOV UP DOWN\n";
+
+/// Writes a solution to a file of its own in the temporary directory
+fn solution(name: &str, src: &str) -> PathBuf {
+    let path = env::temp_dir().join(format!("tis-100-cli-{}-{}.txt", std::process::id(), name));
+    fs::write(&path, src).unwrap();
+    path
+}
+
+/// Runs the binary on the signal amplifier puzzle
+fn run(args: &[&str], solution: &PathBuf) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_tis-100"))
+        .args(["run", "--puzzle", "10981"])
+        .args(args)
+        .arg(solution)
+        .output()
+        .unwrap()
+}
+
+fn stdout(output: &Output) -> String {
+    String::from_utf8(output.stdout.clone()).unwrap()
+}
+
+#[test]
+fn test_cli_pass() {
+    let path = solution("pass", GOOD);
+    let output = run(&[], &path);
+    assert_eq!(output.status.code(), Some(0));
+    assert!(stdout(&output).starts_with("PASS SIGNAL AMPLIFIER: "));
+
+    let output = run(&["--json"], &path);
+    assert_eq!(output.status.code(), Some(0));
+    assert!(stdout(&output).ends_with(",\"nodes\":4,\"instructions\":6}\n"));
+}
+
+#[test]
+fn test_cli_fail() {
+    let path = solution("fail", &GOOD.replace("ADD ACC", "ADD 1"));
+    let output = run(&[], &path);
+    assert_eq!(output.status.code(), Some(1));
+    assert_eq!(stdout(&output).lines().count(), 4);
+
+    /* Every test set is listed, and deadlocks stop early */
+    let path = solution("deadlock", "@0\nMOV LEFT DOWN\n");
+    let output = run(&["--json"], &path);
+    assert_eq!(output.status.code(), Some(1));
+    let json = stdout(&output);
+    assert!(json.starts_with("{\"test_sets\":[{\"seed\":1,\"passed\":false,\"cycles\":"));
+    assert_eq!(json.matches("\"index\":null,\"deadlocked\":true}").count(), 3);
+}
+
+#[test]
+fn test_cli_load_errors() {
+    let path = solution("bogus", "@0\nBOGUS\n");
+    let output = run(&["--json"], &path);
+    assert_eq!(output.status.code(), Some(2));
+    assert!(output.stdout.is_empty());
+    assert!(!output.stderr.is_empty());
+
+    let output = run(&[], &PathBuf::from("/nonexistent/solution.txt"));
+    assert_eq!(output.status.code(), Some(2));
+
+    let output = run(&["--max-cycles", "lots"], &solution("cycles", GOOD));
+    assert_eq!(output.status.code(), Some(2));
+}